@@ -325,21 +325,41 @@ fn convert_raytracer_vec3(vec3: raytracer::Vec3) -> Vec3 {
 }
 
 fn get_albedo(material: &raytracer::Material) -> Vec3 {
-	let albedo = match material {
-		raytracer::Material::Metal { albedo, .. } => *albedo,
-		raytracer::Material::Lambertain { albedo, .. } => *albedo,
-		raytracer::Material::Dielectric { .. } => raytracer::Vec3::one(),
-	};
-	convert_raytracer_vec3(albedo)
+	convert_raytracer_vec3(material.albedo())
 }
 
 fn extract_material(material: &raytracer::Material) -> Material {
 	match material {
 		raytracer::Material::Dielectric { ir } => Material::Dielectric { ir: *ir as f32 },
+		// the gpu renderer is rgb only, so dispersive glass uses its ior at the d-line
+		raytracer::Material::DispersiveDielectric { ior } => Material::Dielectric {
+			ir: ior.at(raytracer::WAVELENGTH_D_LINE) as f32,
+		},
 		raytracer::Material::Lambertain { emission, .. } => Material::Lambertain {
 			emission: *emission as f32,
 		},
 		raytracer::Material::Metal { fuzz, .. } => Material::Metalic { fuzz: *fuzz as f32 },
+		// no volumes on the gpu, subsurface materials are approximated as diffuse
+		raytracer::Material::Subsurface { .. } => Material::Lambertain { emission: 0.0 },
+		// layers and surface maps are not supported on the gpu, only their base is rendered
+		raytracer::Material::ThinFilm { base, .. }
+		| raytracer::Material::Clearcoat { base, .. }
+		| raytracer::Material::NormalMap { base, .. }
+		| raytracer::Material::BumpMap { base, .. }
+		| raytracer::Material::Cutout { base, .. }
+		| raytracer::Material::Layered { base, .. } => extract_material(base),
+		// the gpu picks the material with the larger average weight
+		raytracer::Material::Mix {
+			first,
+			second,
+			weight,
+		} => {
+			if weight.average() > 0.5 {
+				extract_material(second)
+			} else {
+				extract_material(first)
+			}
+		}
 	}
 }
 
@@ -17,6 +17,15 @@ pub struct RenderInfo {
 	pub frame_counter: u32,
 	/// just a bool!
 	normal_sky_color: u32,
+	/// bounces before russian roulette starts terminating paths
+	pub min_depth: u32,
+	/// absolute safety cap for the path length
+	pub max_depth: u32,
+}
+
+impl RenderInfo {
+	pub const DEFAULT_MIN_DEPTH: u32 = 3;
+	pub const DEFAULT_MAX_DEPTH: u32 = 32;
 }
 
 pub struct Renderer {
@@ -107,6 +116,8 @@ impl Renderer {
 		let render_info = RenderInfo {
 			frame_counter: 1,
 			normal_sky_color: normal_sky_color as u32,
+			min_depth: RenderInfo::DEFAULT_MIN_DEPTH,
+			max_depth: RenderInfo::DEFAULT_MAX_DEPTH,
 		};
 		let render_info_uniform_buffer =
 			UniformBuffer::<RenderInfo, FRAME_COUNTER_UNIFORM_BUFFER_BIND_GROUP>::new(
@@ -3,6 +3,10 @@ struct RenderInfo {
 	frame_counter: u32,
 	// just a bool!
 	normal_sky_color: u32,
+	// bounces before russian roulette starts
+	min_depth: u32,
+	// absolute safety cap
+	max_depth: u32,
 }
 
 const PCG_MULTIPLIER: u32 = 747796405u;
@@ -304,11 +308,20 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(workgroup_
     var light = vec3<f32>(0.0);
     var contribution = vec3<f32>(1.0);
 
-    //				  max_depth: 5
-    for (var i = 0u; i < 5u; i++) {
+    for (var i = 0u; i < render_info.max_depth; i++) {
     	if (!ray_color(&light, &contribution, &ray, &pcg_state)) {
      		break;
        	}
+
+    	// russian roulette: paths that can't contribute much anymore get terminated randomly,
+    	// surviving paths are weighted up to keep the result unbiased
+    	if (i + 1u >= render_info.min_depth) {
+    		let survival_probability = min(max(max(contribution.x, contribution.y), contribution.z), 1.0);
+    		if (random_f32(&pcg_state) >= survival_probability) {
+    			break;
+    		}
+    		contribution /= survival_probability;
+    	}
     }
 
     let texture_coord = vec2<i32>(global_id.xy);
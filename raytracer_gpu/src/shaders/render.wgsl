@@ -3,6 +3,8 @@ struct RenderInfo {
 	frame_counter: u32,
 	// just a bool!
 	normal_sky_color: u32,
+	min_depth: u32,
+	max_depth: u32,
 }
 
 @group(0) @binding(0) var output_texture: texture_2d<f32>;
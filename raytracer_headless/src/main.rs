@@ -1,7 +1,31 @@
-use indicatif::ParallelProgressIterator;
-use rayon::prelude::*;
-use raytracer::{BVH, Camera, Scalar, Scene, Vec3, render};
-use std::time::Instant;
+use image::{
+	Delay, DynamicImage, Frame,
+	codecs::gif::{GifEncoder, Repeat},
+};
+use indicatif::ProgressBar;
+use raytracer::{
+	AdaptiveSampling, AnimatedScene, AovLayer, Aperture, BVH, Camera, CameraTrack, Checkpoint,
+	DenoiseSettings, Filter, LensSystem, OdsCamera, OutlierRejection, PhysicalCamera, Projection,
+	ProjectionKind, ProjectionSettings, RealisticCamera, RenderSession, RenderSettings,
+	SamplerKind, Scalar, Scene, StereoCamera, TileOrder, Vec3, sample_heatmap_color,
+};
+use std::{
+	fs::File,
+	io::BufWriter,
+	ops::Range,
+	str::FromStr,
+	time::{Duration, Instant},
+};
+
+// flythrough of the sample scene for --frames without --camera-track
+// time, position, target, fov and focus distance per line
+const DEFAULT_CAMERA_TRACK: &str = "
+	0 13 2 3 0 0.5 0 20 10
+	2 9 2.5 9 0 1 0 25 10
+	4 1 3 12 0 1 0 30 11
+	6 -8 2 8 -2 1 0 25 9
+	8 -13 1.5 -2 -4 1 0 20 9
+";
 
 fn vec3_to_rgb(v: &Vec3) -> image::Rgb<u8> {
 	image::Rgb([
@@ -11,56 +35,592 @@ fn vec3_to_rgb(v: &Vec3) -> image::Rgb<u8> {
 	])
 }
 
+// parses flags in the form of `--name=value`
+fn get_flag_value<T: FromStr>(args: &[String], name: &str) -> Option<T> {
+	args.iter().find_map(|arg| {
+		arg.strip_prefix(name)
+			.and_then(|rest| rest.strip_prefix('='))
+			.map(|value| {
+				value
+					.parse()
+					.unwrap_or_else(|_| panic!("invalid value for {name}: {value}"))
+			})
+	})
+}
+
+// half open range of frames in the form of `N..M`
+fn parse_frame_range(range: &str) -> Option<Range<u32>> {
+	let (start, end) = range.split_once("..")?;
+	Some(start.trim().parse().ok()?..end.trim().parse().ok()?)
+}
+
+// parses flags in the form of `--name=x,y,z`
+fn get_flag_components<const N: usize>(args: &[String], name: &str) -> Option<[Scalar; N]> {
+	let value = get_flag_value::<String>(args, name)?;
+	let components: Vec<Scalar> = value
+		.split(',')
+		.map(|component| component.trim().parse().ok())
+		.collect::<Option<_>>()
+		.unwrap_or_default();
+	Some(components.try_into().unwrap_or_else(|_| {
+		panic!("invalid value for {name}: {value}, expected {N} comma separated numbers")
+	}))
+}
+
+const DEFAULT_SAMPLES: u32 = 800;
+
 fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
 	let width = 2560;
-	let height = 1440;
-	let max_depth = 10;
-	let samples = 800;
-
-	let camera = Camera::new(
-		Vec3::new(13.0, 1.5, 3.0),
-		-Vec3::new(13.0, 1.5, 3.0).normalize(),
-		20.0,
-		10.0,
-		0.6,
-		width,
-		height,
+	// renders whole passes until the next one wouldn't be done in time, without --samples only the
+	// time limit ends the render
+	let time_limit = get_flag_value(&args, "--time-limit").map(Duration::from_secs_f64);
+	let sample_count = get_flag_value(&args, "--samples");
+	let samples = sample_count.unwrap_or(if time_limit.is_some() {
+		u32::MAX
+	} else {
+		DEFAULT_SAMPLES
+	});
+	let projection_name =
+		get_flag_value::<String>(&args, "--projection").unwrap_or("perspective".to_string());
+	let projection_kind = ProjectionKind::from_name(&projection_name).unwrap_or_else(|| {
+		panic!(
+			"unknown projection {projection_name}, available: {}",
+			ProjectionKind::NAMES.join(", ")
+		)
+	});
+	// panoramas cover 360° horizontally and 180° vertically
+	let eye_height = match projection_kind {
+		ProjectionKind::Equirectangular => width / 2,
+		_ => 1440,
+	};
+	let origin = Vec3::new(13.0, 1.5, 3.0);
+	// the perspective camera focuses on the point it looks at
+	let target = get_flag_components(&args, "--look-at").map(|[x, y, z]| Vec3::new(x, y, z));
+	let direction = target.unwrap_or(Vec3::zero()) - origin;
+	let focus_dist = target.map_or(Camera::DEFAULT_FOCUS_DIST, |_| direction.length());
+	let up = get_flag_components(&args, "--up").map(|[x, y, z]| Vec3::new(x, y, z));
+	let roll: Option<Scalar> = get_flag_value(&args, "--roll");
+	// focuses on what the pixel (the center of the image without a value) sees
+	let auto_focus = if args.iter().any(|arg| arg == "--auto-focus") {
+		Some([width as Scalar / 2.0, eye_height as Scalar / 2.0])
+	} else {
+		// the center of the given pixel on the film
+		get_flag_components::<2>(&args, "--auto-focus").map(|[x, y]| [x + 0.5, y + 0.5])
+	};
+	// renders both eyes stacked top-bottom (left eye on top), equirect panoramas become
+	// omni-directional stereo
+	let stereo = args.iter().any(|arg| arg == "--stereo");
+	let ipd = get_flag_value(&args, "--ipd").unwrap_or(StereoCamera::DEFAULT_IPD);
+	let convergence_dist =
+		get_flag_value(&args, "--convergence").unwrap_or(StereoCamera::DEFAULT_CONVERGENCE_DIST);
+	// bokeh shape of the perspective and lens cameras
+	// any of the photographic settings switches the perspective camera to a physical one
+	let physical_flags = [
+		"--focal-length",
+		"--f-number",
+		"--shutter",
+		"--iso",
+		"--focus-dist",
+		"--white-balance",
+	];
+	let physical = args
+		.iter()
+		.any(|arg| physical_flags.iter().any(|flag| arg.starts_with(flag)))
+		.then(|| {
+			let defaults = PhysicalCamera::default();
+			PhysicalCamera {
+				focal_length: get_flag_value(&args, "--focal-length")
+					.unwrap_or(defaults.focal_length),
+				sensor_width: get_flag_value(&args, "--sensor-width")
+					.unwrap_or(defaults.sensor_width),
+				f_number: get_flag_value(&args, "--f-number").unwrap_or(defaults.f_number),
+				shutter_time: get_flag_value(&args, "--shutter").unwrap_or(defaults.shutter_time),
+				iso: get_flag_value(&args, "--iso").unwrap_or(defaults.iso),
+				focus_dist: get_flag_value(&args, "--focus-dist").unwrap_or(focus_dist),
+				white_balance: get_flag_value(&args, "--white-balance"),
+			}
+		});
+	let mut aperture = Aperture::polygonal(
+		get_flag_value(&args, "--blades").unwrap_or(0),
+		get_flag_value(&args, "--blade-rotation").unwrap_or(0.0),
 	);
+	if let Some(cat_eye) = get_flag_value(&args, "--cat-eye") {
+		aperture = aperture.with_cat_eye(cat_eye);
+	}
+	// traces through a lens description file (or the built in double-gauss-50mm) instead
+	let lens = get_flag_value::<String>(&args, "--lens").map(|name| {
+		let lens = if name == "double-gauss-50mm" {
+			LensSystem::double_gauss_50mm()
+		} else {
+			LensSystem::open(&name).unwrap_or_else(|error| panic!("invalid lens {name}: {error}"))
+		};
+		match get_flag_value(&args, "--aperture-diameter") {
+			Some(diameter) => lens.with_aperture_diameter(diameter),
+			None => lens,
+		}
+	});
+	// renders frame_<n>.png for every frame of the range along a camera track instead
+	let frames = get_flag_value::<String>(&args, "--frames").map(|range| {
+		parse_frame_range(&range)
+			.unwrap_or_else(|| panic!("invalid value for --frames: {range}, expected N..M"))
+	});
+	// the photographic settings and the look-at options only exist for the thin lens camera, camera
+	// tracks, stereo rigs, lens systems and the other projections have no exposure, shutter or roll
+	let thin_lens_image = !stereo
+		&& projection_kind == ProjectionKind::Perspective
+		&& lens.is_none()
+		&& frames.is_none();
+	if physical.is_some() && !thin_lens_image {
+		panic!(
+			"{} only work for single images of the perspective camera without --stereo and --lens",
+			physical_flags.join(", ")
+		);
+	}
+	if (up.is_some() || roll.is_some() || auto_focus.is_some()) && !thin_lens_image {
+		panic!(
+			"--up, --roll and --auto-focus only work for single images of the perspective camera \
+			 without --stereo and --lens"
+		);
+	}
+	// static scenes are animations without any tracks
+	let scene = match args.iter().find(|arg| !arg.starts_with("--")) {
+		Some(arg) if arg == "animated" => AnimatedScene::create_sample_animation(),
+		scene_name => AnimatedScene::new(match scene_name {
+			Some(arg) if arg == "csg" => Scene::create_csg_scene(),
+			Some(arg) if arg == "dispersion" => Scene::create_dispersion_scene(),
+			Some(arg) if arg == "coating" => Scene::create_coating_scene(),
+			Some(arg) if arg == "subsurface" => Scene::create_subsurface_scene(),
+			Some(arg) if arg == "maps" => Scene::create_surface_maps_scene(),
+			Some(arg) if arg == "cutout" => Scene::create_cutout_scene(),
+			Some(arg) if arg == "mix" => Scene::create_mix_scene(),
+			_ => Scene::create_sample_scene(),
+		}),
+	};
+	// single images show the scene at --time, physical cameras blur what moves while the shutter is
+	// open
+	let time = get_flag_value(&args, "--time").unwrap_or(0.0);
+	let bvh = scene
+		.bvh_at(time, physical.map_or(0.0, |physical| physical.shutter_time))
+		.unwrap();
 
-	let bvh = BVH::new(Scene::create_sample_scene()).unwrap();
-
-	let render_start = Instant::now();
-
-	let mut output = vec![Vec3::zero(); width * height];
-	output
-		.par_chunks_exact_mut(width)
-		.progress()
-		.enumerate()
-		.for_each(|(y, row)| {
-			let mut rand = rand::rng();
-			for (x, output_color) in row.iter_mut().enumerate() {
-				let mut final_color = Vec3::zero();
-				for _ in 0..samples {
-					final_color = final_color
-						+ render(
-							x as Scalar,
-							y as Scalar,
-							&camera,
-							&bvh,
-							max_depth,
-							&mut rand,
-						)
-						.linear_to_gamma();
+	let (camera, height): (Box<dyn Projection>, usize) = match (stereo, projection_kind, lens) {
+		(false, ProjectionKind::Perspective, Some(lens)) => (
+			Box::new(
+				RealisticCamera::new(
+					origin,
+					direction,
+					lens,
+					get_flag_value(&args, "--sensor-width")
+						.unwrap_or(RealisticCamera::DEFAULT_SENSOR_WIDTH),
+					focus_dist,
+					width,
+					eye_height,
+				)
+				.with_aperture(aperture),
+			),
+			eye_height,
+		),
+		(false, ProjectionKind::Perspective, None) => {
+			// physical cameras are rebuilt for a new focus distance, their field of view and
+			// depth of field depend on it
+			let build_camera = |focus_dist: Scalar| {
+				match &physical {
+					Some(physical) => Camera::physical(
+						origin,
+						direction,
+						&PhysicalCamera {
+							focus_dist,
+							..*physical
+						},
+						width,
+						eye_height,
+					),
+					None => {
+						Camera::new(origin, direction, 20.0, focus_dist, 0.6, width, eye_height)
+					}
 				}
-				*output_color = final_color / samples as Scalar;
+				.with_up(up.unwrap_or(Camera::WORLD_UP))
+				.with_roll(roll.unwrap_or(0.0))
+				.with_aperture(aperture)
+			};
+			let mut camera = build_camera(physical.map_or(focus_dist, |p| p.focus_dist));
+			if let Some([x, y]) = auto_focus
+				&& let Some(focus_dist) = camera.measure_focus_dist(&bvh, x, y)
+			{
+				println!("Focus distance: {focus_dist}");
+				camera = build_camera(focus_dist);
 			}
+			(Box::new(camera), eye_height)
+		}
+		(false, _, None) => (
+			projection_kind.create(
+				&ProjectionSettings {
+					origin,
+					direction,
+					fov: 20.0,
+					focus_dist: 10.0,
+					defocus_angle: 0.6,
+				},
+				width,
+				eye_height,
+			),
+			eye_height,
+		),
+		(true, ProjectionKind::Perspective, None) => (
+			Box::new(StereoCamera::new(
+				origin,
+				direction,
+				20.0,
+				ipd,
+				convergence_dist,
+				width,
+				eye_height,
+			)),
+			eye_height * 2,
+		),
+		(true, ProjectionKind::Equirectangular, None) => (
+			Box::new(OdsCamera::new(origin, direction, ipd, width, eye_height)),
+			eye_height * 2,
+		),
+		(true, _, None) => {
+			panic!("--stereo only works with the perspective and equirect projections")
+		}
+		(_, _, Some(_)) => panic!("--lens only works with the perspective projection"),
+	};
+
+	let mut settings = RenderSettings::default();
+	if let Some(min_depth) = get_flag_value(&args, "--min-depth") {
+		settings.min_depth = min_depth;
+	}
+	if let Some(max_depth) = get_flag_value(&args, "--max-depth") {
+		settings.max_depth = max_depth;
+	}
+	// firefly suppression, all of them are off by default to keep the render unbiased
+	settings.max_indirect_radiance = get_flag_value(&args, "--clamp");
+	settings.regularization = get_flag_value(&args, "--regularize");
+	let outlier_rejection = args.iter().any(|arg| arg == "--reject-outliers").then(|| {
+		let mut outlier_rejection = OutlierRejection::default();
+		if let Some(sigma) = get_flag_value(&args, "--outlier-sigma") {
+			outlier_rejection.sigma = sigma;
+		}
+		outlier_rejection
+	});
+	settings.spectral = args.iter().any(|arg| arg == "--spectral");
+	// writes every aov as output_<name>.exr next to output.png
+	let write_aovs = args.iter().any(|arg| arg == "--aovs");
+	// filters output.png with the albedo and normal aovs as feature buffers
+	let denoise_output = args.iter().any(|arg| arg == "--denoise");
+	// samples becomes the max spp, pixels stop early once their noise is below the threshold
+	let sampling = if args.iter().any(|arg| arg == "--adaptive") {
+		let mut sampling = AdaptiveSampling::new(samples);
+		if let Some(min_samples) = get_flag_value(&args, "--min-samples") {
+			sampling.min_samples = min_samples;
+		}
+		if let Some(noise_threshold) = get_flag_value(&args, "--noise-threshold") {
+			sampling.noise_threshold = noise_threshold;
+		}
+		sampling
+	} else {
+		AdaptiveSampling::fixed(samples)
+	};
+	let sampler_name = get_flag_value::<String>(&args, "--sampler").unwrap_or("sobol".to_string());
+	// the strata need a real sample count, renders that only end with the time limit stratify the
+	// default samples and continue with independent ones
+	let strata = sample_count.unwrap_or(DEFAULT_SAMPLES);
+	let sampler_kind = SamplerKind::from_name(&sampler_name, strata).unwrap_or_else(|| {
+		panic!(
+			"unknown sampler {sampler_name}, available: {}",
+			SamplerKind::NAMES.join(", ")
+		)
+	});
+	// reconstruction filter the samples are weighted with
+	let filter_name = get_flag_value::<String>(&args, "--filter").unwrap_or("box".to_string());
+	let filter = Filter::from_name(&filter_name).unwrap_or_else(|| {
+		panic!(
+			"unknown filter {filter_name}, available: {}",
+			Filter::NAMES.join(", ")
+		)
+	});
+	// size and order of the buckets the image is rendered in
+	let tile_size =
+		get_flag_value(&args, "--tile-size").unwrap_or(RenderSession::DEFAULT_TILE_SIZE);
+	let tile_order_name =
+		get_flag_value::<String>(&args, "--tile-order").unwrap_or("spiral".to_string());
+	let tile_order = TileOrder::from_name(&tile_order_name).unwrap_or_else(|| {
+		panic!(
+			"unknown tile order {tile_order_name}, available: {}",
+			TileOrder::NAMES.join(", ")
+		)
+	});
+	// every frontend setting of a session except for the scene and the camera
+	let create_session = |bvh: BVH, camera: Box<dyn Projection>, aovs: bool| {
+		let session = RenderSession::new(bvh, camera, settings, width, height)
+			.with_sampling(sampling)
+			.with_sampler(sampler_kind)
+			.with_filter(filter)
+			.with_tiles(tile_size, tile_order);
+		let session = match outlier_rejection {
+			Some(outlier_rejection) => session.with_outlier_rejection(outlier_rejection),
+			None => session,
+		};
+		if aovs { session.with_aovs() } else { session }
+	};
+	// writes the spent samples per pixel as output_samples.png
+	let write_sample_heatmap = args.iter().any(|arg| arg == "--sample-heatmap");
+	let write_gif = args.iter().any(|arg| arg == "--gif");
+	let write_apng = args.iter().any(|arg| arg == "--apng");
+	if frames.is_some()
+		&& (stereo
+			|| projection_kind != ProjectionKind::Perspective
+			|| args.iter().any(|arg| arg.starts_with("--lens")))
+	{
+		panic!("--frames only works with the perspective camera");
+	}
+	// saves the film every interval (and once it is finished) to continue it with --resume, or to
+	// add more samples to it later with a higher --samples
+	let resume = args.iter().any(|arg| arg == "--resume");
+	// --checkpoint-interval alone doesn't turn checkpoints on
+	let checkpointing = args
+		.iter()
+		.any(|arg| arg == "--checkpoint" || arg.starts_with("--checkpoint="));
+	let checkpoint = (resume || checkpointing).then(|| {
+		let path = get_flag_value::<String>(&args, "--checkpoint")
+			.unwrap_or("output.checkpoint".to_string());
+		let interval = get_flag_value(&args, "--checkpoint-interval").unwrap_or(60.0);
+		(path, Duration::from_secs_f64(interval))
+	});
+	if frames.is_some() && checkpoint.is_some() {
+		panic!("--checkpoint and --resume only work for single images");
+	}
+
+	if let Some(frames) = &frames {
+		let track = match get_flag_value::<String>(&args, "--camera-track") {
+			Some(path) => CameraTrack::open(&path)
+				.unwrap_or_else(|error| panic!("invalid camera track {path}: {error}")),
+			None => CameraTrack::parse(DEFAULT_CAMERA_TRACK).unwrap(),
+		};
+		let fps: u16 = get_flag_value(&args, "--fps").unwrap_or(24);
+		// fraction of a frame the shutter is open (0.5 is a 180° shutter), 0 disables motion blur
+		let motion_blur: Scalar = get_flag_value(&args, "--motion-blur").unwrap_or(0.0);
+		let shutter_time = motion_blur / fps as Scalar;
+		// the animations are encoded while the frames are rendered instead of keeping every frame
+		let mut gif = write_gif.then(|| {
+			create_gif("animation.gif")
+				.unwrap_or_else(|e| panic!("failed to save to animation.gif: {e}"))
+		});
+		let mut apng = (write_apng && !frames.is_empty()).then(|| {
+			create_apng("animation.png", width, height, frames.len() as u32, fps)
+				.unwrap_or_else(|e| panic!("failed to save to animation.png: {e}"))
 		});
+		for frame in frames.clone() {
+			// frame 0 is at the first keyframe of the camera track, the animated scene runs on the
+			// same clock (a track starting at 2s shows the scene from 2s on)
+			let time = track.start_time() + frame as Scalar / fps as Scalar;
+			let camera = track
+				.camera(time, 0.6, width, height)
+				.with_shutter_time(shutter_time)
+				.with_aperture(aperture);
+			println!("Frame {frame} (t = {time}s)");
+			let session = render_frame(
+				create_session(
+					scene.bvh_at(time, shutter_time).unwrap(),
+					Box::new(camera),
+					denoise_output,
+				),
+				&FrameOptions {
+					time_limit,
+					..Default::default()
+				},
+			);
+			let image = finish_frame(&session, denoise_output);
+			let path = format!("frame_{frame:04}.png");
+			image
+				.save(&path)
+				.unwrap_or_else(|e| panic!("failed to save to {path}: {e}"));
+			if let Some(gif) = &mut gif {
+				gif.encode_frame(gif_frame(&image, fps))
+					.unwrap_or_else(|e| panic!("failed to save to animation.gif: {e}"));
+			}
+			if let Some(apng) = &mut apng {
+				apng.write_image_data(image.as_raw())
+					.unwrap_or_else(|e| panic!("failed to save to animation.png: {e}"));
+			}
+		}
+		if let Some(apng) = apng {
+			apng.finish()
+				.unwrap_or_else(|e| panic!("failed to save to animation.png: {e}"));
+		}
+		return;
+	}
 
+	let render_start = Instant::now();
+	let mut session = create_session(bvh, camera, write_aovs || denoise_output);
+	if resume && let Some((path, _)) = &checkpoint {
+		let checkpoint =
+			Checkpoint::open(path).unwrap_or_else(|error| panic!("failed to resume: {error}"));
+		session
+			.restore(checkpoint)
+			.unwrap_or_else(|error| panic!("failed to resume from {path}: {error}"));
+		println!("Resuming from {path} after pass {}", session.pass());
+	}
+	// writes the current state of the image to output.png every n seconds
+	let preview_interval = get_flag_value(&args, "--preview-interval").map(Duration::from_secs_f64);
+	let session = render_frame(
+		session,
+		&FrameOptions {
+			checkpoint,
+			time_limit,
+			preview_interval,
+		},
+	);
+	let film = session.film();
 	println!(
 		"Rendering took {}s",
 		(Instant::now() - render_start).as_secs_f32()
 	);
+	println!(
+		"Average samples per pixel: {}",
+		film.pixels
+			.iter()
+			.map(|pixel| pixel.sample_count() as u64)
+			.sum::<u64>() as f64
+			/ film.pixels.len() as f64
+	);
+	if film.outlier_rejection.is_some() {
+		println!(
+			"Rejected outlier samples: {}",
+			film.pixels
+				.iter()
+				.map(|pixel| pixel.rejected_count() as u64)
+				.sum::<u64>()
+		);
+	}
+
+	finish_frame(&session, denoise_output)
+		.save("output.png")
+		.expect("failed to save to output.png");
+
+	if write_sample_heatmap {
+		// a time limited render has no sample count to compare with
+		let max_samples = if samples == u32::MAX {
+			film.pixels
+				.iter()
+				.map(|pixel| pixel.sample_count())
+				.max()
+				.unwrap_or(1)
+		} else {
+			samples
+		};
+		let mut heatmap = image::RgbImage::new(width as u32, height as u32);
+		for y in 0..height {
+			for x in 0..width {
+				let color =
+					sample_heatmap_color(film.pixels[y * width + x].sample_count(), max_samples);
+				heatmap.put_pixel(x as u32, y as u32, vec3_to_rgb(&color));
+			}
+		}
+		heatmap
+			.save("output_samples.png")
+			.expect("failed to save to output_samples.png");
+	}
+
+	if write_aovs && let Some(aov_buffer) = session.aovs() {
+		for layer in AovLayer::ALL {
+			let path = format!("output_{}.exr", layer.name());
+			aov_buffer
+				.get_layer_image(layer)
+				.save(&path)
+				.unwrap_or_else(|e| panic!("failed to save to {path}: {e}"));
+		}
+	}
+}
+
+// when to stop rendering an image and what to save while it is rendered
+#[derive(Debug, Default)]
+struct FrameOptions {
+	// path and interval of the checkpoints
+	checkpoint: Option<(String, Duration)>,
+	// no pass is started that wouldn't be done before it
+	time_limit: Option<Duration>,
+	// the image so far is written to output.png this often
+	preview_interval: Option<Duration>,
+}
+
+// renders passes until every pixel is finished or the time is up, with a progress bar over the
+// passes
+fn render_frame(session: RenderSession, options: &FrameOptions) -> RenderSession {
+	let progress_bar = ProgressBar::no_length();
+	let mut session = session.with_progress({
+		let progress_bar = progress_bar.clone();
+		move |progress| {
+			// without a sample count only the time limit ends the render
+			if progress.max_samples != u32::MAX {
+				progress_bar.set_length(progress.max_samples as u64);
+			}
+			progress_bar.set_position(progress.pass as u64);
+		}
+	});
+	let save_checkpoint = |session: &RenderSession| {
+		if let Some((path, _)) = &options.checkpoint
+			&& let Err(error) = session.checkpoint().save(path)
+		{
+			eprintln!("failed to save checkpoint: {error}");
+		}
+	};
+	let render_start = Instant::now();
+	let mut last_checkpoint = render_start;
+	let mut last_preview = render_start;
+	loop {
+		let pass_start = Instant::now();
+		if session.step() == 0 {
+			break;
+		}
+		let now = Instant::now();
+		if let Some((_, interval)) = &options.checkpoint
+			&& now - last_checkpoint >= *interval
+		{
+			save_checkpoint(&session);
+			last_checkpoint = now;
+		}
+		if let Some(interval) = options.preview_interval
+			&& now - last_preview >= interval
+		{
+			if let Err(error) = session.resolve_to_image().save("output.png") {
+				eprintln!("failed to save preview to output.png: {error}");
+			}
+			last_preview = now;
+		}
+		// the next pass takes about as long as the last one
+		if let Some(time_limit) = options.time_limit
+			&& (now - render_start) + (now - pass_start) > time_limit
+		{
+			println!("Time limit reached after {} passes", session.pass());
+			break;
+		}
+	}
+	save_checkpoint(&session);
+	progress_bar.finish();
+	session
+}
 
+// resolved film, denoised with the aovs as feature buffers when requested
+fn finish_frame(session: &RenderSession, denoise_output: bool) -> image::RgbImage {
+	if denoise_output {
+		let denoise_start = Instant::now();
+		let output = session.resolve_denoised(&DenoiseSettings::default());
+		println!(
+			"Denoising took {}s",
+			(Instant::now() - denoise_start).as_secs_f32()
+		);
+		let film = session.film();
+		to_image(&output, film.width, film.height)
+	} else {
+		session.resolve_to_image()
+	}
+}
+
+fn to_image(output: &[Vec3], width: usize, height: usize) -> image::RgbImage {
 	let mut image = image::RgbImage::new(width as u32, height as u32);
 	for y in 0..height {
 		for x in 0..width {
@@ -68,6 +628,37 @@ fn main() {
 		}
 	}
 	image
-		.save("output.png")
-		.expect("failed to save to output.png");
+}
+
+fn create_gif(path: &str) -> image::ImageResult<GifEncoder<BufWriter<File>>> {
+	let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(path)?), 10);
+	encoder.set_repeat(Repeat::Infinite)?;
+	Ok(encoder)
+}
+
+fn gif_frame(image: &image::RgbImage, fps: u16) -> Frame {
+	Frame::from_parts(
+		DynamicImage::ImageRgb8(image.clone()).into_rgba8(),
+		0,
+		0,
+		Delay::from_numer_denom_ms(1000, fps as u32),
+	)
+}
+
+// image can only decode animated pngs, they are encoded with the png crate directly, the frames
+// are written one after another with write_image_data
+fn create_apng(
+	path: &str,
+	width: usize,
+	height: usize,
+	frame_count: u32,
+	fps: u16,
+) -> Result<png::Writer<BufWriter<File>>, png::EncodingError> {
+	let file = BufWriter::new(File::create(path)?);
+	let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+	encoder.set_color(png::ColorType::Rgb);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.set_animated(frame_count, 0)?;
+	encoder.set_frame_delay(1, fps)?;
+	encoder.write_header()
 }
@@ -1,7 +1,9 @@
 use minifb::MouseButton;
-use minifb::{Key, Window, WindowOptions};
-use rayon::prelude::*;
-use raytracer::{BVH, Camera, Scalar, Scene, Vec3, get_camera_rotation, render};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use raytracer::{
+	AdaptiveSampling, AnimatedScene, Camera, DenoiseSettings, RenderSession, RenderSettings,
+	Scalar, Scene, TileOrder, Vec3, get_camera_rotation,
+};
 use std::time::Instant;
 
 fn vec3_to_rgb(v: &Vec3) -> [u8; 3] {
@@ -16,19 +18,24 @@ fn rgb_to_u32(r: u32, g: u32, b: u32) -> u32 {
 	(r << 16) | (g << 8) | b
 }
 
+// the denoiser only helps while the accumulated image is still noisy
+const DENOISE_MAX_FRAMES: u32 = 64;
+// tiles rendered per frame while buckets are shown
+const BUCKETS_PER_FRAME: usize = 8;
+const BUCKET_SIZE: usize = 16;
+
 fn main() {
 	let width = 300; //2560;
 	let height = 200; //1440;//(width * (16 / 9)) as usize;
-	let max_depth = 10; //50;
+	let settings = RenderSettings::default();
 
-	let mut accum_image: Vec<Vec3> = Vec::new();
-	accum_image.resize(width * height, Vec3::zero());
-	let mut frame_count = 1;
-	let mut final_image: Vec<Vec3> = Vec::new();
-	final_image.resize(width * height, Vec3::zero());
+	let mut denoise_enabled = false;
+	let mut title = "Raytracer - Runtime";
+	// renders a few tiles per frame instead of whole passes, so they can be seen filling in
+	let mut show_buckets = false;
 
 	let mut window = Window::new(
-		"Raytracer - Runtime",
+		title,
 		width,
 		height,
 		WindowOptions {
@@ -53,7 +60,24 @@ fn main() {
 		height,
 	);
 
-	let bvh = BVH::new(Scene::create_sample_scene()).unwrap();
+	// `animated` plays the sample animation in a loop, P pauses and resumes it
+	let scene = match std::env::args().nth(1) {
+		Some(arg) if arg == "animated" => AnimatedScene::create_sample_animation(),
+		_ => AnimatedScene::new(Scene::create_sample_scene()),
+	};
+	let mut playing = scene.duration() > 0.0;
+	let mut animation_time = 0.0;
+	let mut session = RenderSession::new(
+		scene.bvh_at(animation_time, 0.0).unwrap(),
+		Box::new(camera.clone()),
+		settings,
+		width,
+		height,
+	)
+	// accumulates until the view changes
+	.with_sampling(AdaptiveSampling::fixed(u32::MAX))
+	.with_aovs()
+	.with_tiles(BUCKET_SIZE, TileOrder::Spiral);
 
 	let mut last_mouse_pos: (Scalar, Scalar) = window
 		.get_mouse_pos(minifb::MouseMode::Clamp)
@@ -66,32 +90,52 @@ fn main() {
 		let delta_time = (now - last_update).as_secs_f64() as Scalar;
 		last_update = now;
 
-		accum_image
-			.par_chunks_exact_mut(width)
-			.enumerate()
-			.for_each(|(y, row)| {
-				let mut rand = rand::rng();
-				for (x, output_color) in row.iter_mut().enumerate() {
-					*output_color = *output_color
-						+ render(
-							x as Scalar,
-							y as Scalar,
-							&camera,
-							&bvh,
-							max_depth,
-							&mut rand,
-						)
-						.linear_to_gamma();
-				}
-			});
-
-		for y in 0..height {
-			for x in 0..width {
-				let image_index = y * width + x;
-				final_image[image_index] = accum_image[image_index] / (frame_count as Scalar);
-			}
+		if window.is_key_pressed(Key::P, KeyRepeat::No) {
+			playing = !playing && scene.duration() > 0.0;
+		}
+		// every frame of the animation starts a new accumulation
+		if playing {
+			animation_time = (animation_time + delta_time) % scene.duration();
+			session.set_bvh(scene.bvh_at(animation_time, 0.0).unwrap());
+		}
+
+		// features are collected even when the denoiser is off, so it can be toggled anytime
+		let denoise_frame = session.pass() < DENOISE_MAX_FRAMES;
+		session.set_collect_aovs(denoise_frame);
+		if show_buckets {
+			session.step_tiles(BUCKETS_PER_FRAME);
+		} else {
+			session.step();
+		}
+
+		if window.is_key_pressed(Key::N, KeyRepeat::No) {
+			denoise_enabled = !denoise_enabled;
+		}
+		// the title shows what the image actually is, the denoiser turns itself off once enough
+		// frames are accumulated
+		let new_title = match (denoise_enabled, denoise_frame) {
+			(true, true) => "Raytracer - Runtime (denoised)",
+			(true, false) => "Raytracer - Runtime (converged, denoiser paused)",
+			(false, _) => "Raytracer - Runtime",
+		};
+		if new_title != title {
+			title = new_title;
+			window.set_title(title);
+		}
+
+		let final_image: Vec<Vec3> = if denoise_enabled && denoise_frame {
+			session.resolve_denoised(&DenoiseSettings::default())
+		} else {
+			session
+				.resolve()
+				.iter()
+				.map(|color| color.linear_to_gamma())
+				.collect()
+		};
+
+		if window.is_key_pressed(Key::B, KeyRepeat::No) {
+			show_buckets = !show_buckets;
 		}
-		frame_count += 1;
 
 		let mouse_pos = window
 			.get_mouse_pos(minifb::MouseMode::Clamp)
@@ -99,9 +143,6 @@ fn main() {
 			.unwrap();
 
 		if window.get_mouse_down(MouseButton::Right) {
-			accum_image.resize(0, Vec3::zero());
-			accum_image.resize(width * height, Vec3::zero());
-
 			// camera rotation
 			yaw += (mouse_pos.0 - last_mouse_pos.0) * 0.25;
 			pitch += (last_mouse_pos.1 - mouse_pos.1) * 0.25;
@@ -146,7 +187,7 @@ fn main() {
 				width,
 				height,
 			);
-			frame_count = 1;
+			session.set_camera(Box::new(camera.clone()));
 		}
 		last_mouse_pos = mouse_pos;
 
@@ -7,10 +7,9 @@ use crossterm::{
 	execute,
 	terminal::{self, ClearType},
 };
-use rayon::prelude::*;
 use raytracer::{
-	BVH, Camera, Scalar, Vec3, combine_spheres_and_cubes, create_simple_scene, get_camera_rotation,
-	render,
+	AdaptiveSampling, BVH, Camera, RenderSession, RenderSettings, Vec3, combine_spheres_and_cubes,
+	create_simple_scene, get_camera_rotation,
 };
 use raytracer_terminal::{CameraController, Cell, FrameBuffer, draw_to_terminal};
 use std::{io, time::Instant};
@@ -47,8 +46,15 @@ fn main() -> io::Result<()> {
 	let mut old_camera = camera.clone();
 
 	let mut fb = FrameBuffer::new(width as usize, height as usize);
-	let mut accum_image = vec![Vec3::zero(); fb.width * fb.height];
-	let mut frame_counter = 1;
+	let settings = RenderSettings {
+		min_depth: 2,
+		max_depth: 8,
+		..Default::default()
+	};
+	// accumulates until the view changes
+	let mut session =
+		RenderSession::new(bvh, Box::new(camera.clone()), settings, fb.width, fb.height)
+			.with_sampling(AdaptiveSampling::fixed(u32::MAX));
 	let mut last_update = Instant::now();
 	let mut camera_controller = CameraController::default();
 
@@ -77,14 +83,13 @@ fn main() -> io::Result<()> {
 				&mut width,
 				&mut height,
 				&mut fb,
-				&mut accum_image,
-				&mut frame_counter,
+				&mut session,
+				&camera,
 			);
 		}
 
-		render_scene(&mut accum_image, fb.width, &bvh, &camera);
-		draw_to_terminal(&mut fb, &accum_image, frame_counter, delta_time);
-		frame_counter += 1;
+		session.step();
+		draw_to_terminal(&mut fb, session.film(), delta_time);
 
 		fb.flush(&mut stdout)?;
 
@@ -119,8 +124,8 @@ fn reset(
 	width: &mut u16,
 	height: &mut u16,
 	framebuffer: &mut FrameBuffer,
-	accum_image: &mut Vec<Vec3>,
-	frame_counter: &mut usize,
+	session: &mut RenderSession,
+	camera: &Camera,
 ) {
 	*width = new_width;
 	*height = new_height;
@@ -129,22 +134,9 @@ fn reset(
 	framebuffer
 		.cells
 		.resize(framebuffer.width * framebuffer.height, Cell::BLANK);
-	*accum_image = vec![Vec3::zero(); framebuffer.width * framebuffer.height];
-	*frame_counter = 1;
-}
-
-fn render_scene(accum_image: &mut [Vec3], width: usize, bvh: &BVH, camera: &Camera) {
-	let max_depth = 4;
-
-	accum_image
-		.par_chunks_exact_mut(width)
-		.enumerate()
-		.for_each(|(y, row)| {
-			let mut rand = rand::rng();
-			for (x, output_color) in row.iter_mut().enumerate() {
-				*output_color = *output_color
-					+ render(x as Scalar, y as Scalar, camera, bvh, max_depth, &mut rand)
-						.linear_to_gamma();
-			}
-		});
+	session.resize(
+		framebuffer.width,
+		framebuffer.height,
+		Box::new(camera.clone()),
+	);
 }
@@ -1,16 +1,10 @@
 use crate::{Cell, FrameBuffer};
-use raytracer::{Scalar, Vec3};
+use raytracer::{Film, Scalar, Vec3};
 use std::time::Duration;
 
-pub fn draw_to_terminal(
-	framebuffer: &mut FrameBuffer,
-	accum_image: &[Vec3],
-	frame_counter: usize,
-	delta_time: Duration,
-) {
-	for (i, cell) in framebuffer.cells.iter_mut().enumerate() {
-		let final_color = accum_image[i] / frame_counter as Scalar;
-		*cell = color_to_cell(final_color);
+pub fn draw_to_terminal(framebuffer: &mut FrameBuffer, film: &Film, delta_time: Duration) {
+	for (cell, pixel) in framebuffer.cells.iter_mut().zip(&film.pixels) {
+		*cell = color_to_cell(pixel.color().linear_to_gamma());
 	}
 
 	let fps = 1.0 / delta_time.as_secs_f32();
@@ -0,0 +1,114 @@
+use crate::{
+	Scalar, Vec3,
+	checkpoint::{read_scalar, read_u32, write_scalar, write_u32},
+};
+use std::io::{self, Read, Write};
+
+// Running mean and variance of the luminance of a pixel's samples (Welford's algorithm)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelVariance {
+	sample_count: u32,
+	mean: Scalar,
+	m2: Scalar,
+}
+
+impl PixelVariance {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[inline(always)]
+	pub fn add_sample(&mut self, color: Vec3) {
+		let luminance = color.luminance();
+		self.sample_count += 1;
+		let delta = luminance - self.mean;
+		self.mean += delta / self.sample_count as Scalar;
+		self.m2 += delta * (luminance - self.mean);
+	}
+
+	pub fn sample_count(&self) -> u32 {
+		self.sample_count
+	}
+
+	pub fn mean(&self) -> Scalar {
+		self.mean
+	}
+
+	pub fn variance(&self) -> Scalar {
+		if self.sample_count < 2 {
+			Scalar::INFINITY
+		} else {
+			self.m2 / (self.sample_count - 1) as Scalar
+		}
+	}
+
+	// standard error of the mean luminance, how much the pixel would still change with more samples
+	pub fn error(&self) -> Scalar {
+		(self.variance() / self.sample_count as Scalar).sqrt()
+	}
+
+	pub(crate) fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+		write_u32(writer, self.sample_count)?;
+		write_scalar(writer, self.mean)?;
+		write_scalar(writer, self.m2)
+	}
+
+	pub(crate) fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+		Ok(Self {
+			sample_count: read_u32(reader)?,
+			mean: read_scalar(reader)?,
+			m2: read_scalar(reader)?,
+		})
+	}
+}
+
+// Spends at least min_samples on every pixel and stops at max_samples or once the error of a
+// pixel is below the noise threshold
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+	pub min_samples: u32,
+	pub max_samples: u32,
+	pub noise_threshold: Scalar,
+}
+
+impl AdaptiveSampling {
+	pub const DEFAULT_MIN_SAMPLES: u32 = 16;
+	// colors are expected in gamma space, so this is roughly 2.5 / 255
+	pub const DEFAULT_NOISE_THRESHOLD: Scalar = 0.01;
+
+	pub fn new(max_samples: u32) -> Self {
+		Self {
+			min_samples: Self::DEFAULT_MIN_SAMPLES.min(max_samples),
+			max_samples,
+			noise_threshold: Self::DEFAULT_NOISE_THRESHOLD,
+		}
+	}
+
+	// every pixel gets exactly `samples` samples
+	pub fn fixed(samples: u32) -> Self {
+		Self {
+			min_samples: samples,
+			max_samples: samples,
+			noise_threshold: 0.0,
+		}
+	}
+
+	#[inline(always)]
+	pub fn is_finished(&self, variance: &PixelVariance) -> bool {
+		variance.sample_count() >= self.max_samples
+			|| (variance.sample_count() >= self.min_samples
+				&& variance.error() <= self.noise_threshold)
+	}
+}
+
+// blue for few samples over green to red for max_samples
+pub fn sample_heatmap_color(samples: u32, max_samples: u32) -> Vec3 {
+	let t = (samples as Scalar / max_samples.max(1) as Scalar).clamp(0.0, 1.0);
+	if t < 0.5 {
+		let t = t * 2.0;
+		Vec3::new(0.0, t, 1.0 - t)
+	} else {
+		let t = (t - 0.5) * 2.0;
+		Vec3::new(t, 1.0 - t, 0.0)
+	}
+}
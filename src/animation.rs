@@ -0,0 +1,697 @@
+use crate::{
+	BVH, Bounded, Camera, Csg, Cube, Material, Moving, Object, Scalar, Scene, Sphere, Vec3,
+};
+use std::{
+	ops::{Add, Mul, Sub},
+	path::Path,
+};
+
+// Camera state at a point in time (in seconds)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraKeyframe {
+	pub time: Scalar,
+	pub position: Vec3,
+	pub target: Vec3,
+	// vertical, in degrees
+	pub fov: Scalar,
+	pub focus_dist: Scalar,
+}
+
+// Keyframed camera path, positions, targets, fov and focus are interpolated with catmull-rom
+// splines so the camera moves smoothly through all keyframes
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraTrack {
+	// sorted by time
+	keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraTrack {
+	pub fn new(mut keyframes: Vec<CameraKeyframe>) -> Result<Self, String> {
+		if keyframes.is_empty() {
+			return Err("camera track has no keyframes".to_string());
+		}
+		keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+		if keyframes
+			.windows(2)
+			.any(|pair| pair[0].time == pair[1].time)
+		{
+			return Err("camera track has two keyframes at the same time".to_string());
+		}
+		Ok(Self { keyframes })
+	}
+
+	// one keyframe per line: time, position (x y z), target (x y z), fov and focus distance,
+	// everything after a # is a comment
+	pub fn parse(description: &str) -> Result<Self, String> {
+		let mut keyframes = Vec::new();
+		for (line_index, line) in description.lines().enumerate() {
+			let line = line.split('#').next().unwrap_or_default().trim();
+			if line.is_empty() {
+				continue;
+			}
+			let values = line
+				.split_whitespace()
+				.map(|value| value.parse::<Scalar>())
+				.collect::<Result<Vec<_>, _>>()
+				.map_err(|error| format!("line {}: {error}", line_index + 1))?;
+			let [time, px, py, pz, tx, ty, tz, fov, focus_dist] = values[..] else {
+				return Err(format!(
+					"line {}: expected 9 values, got {}",
+					line_index + 1,
+					values.len()
+				));
+			};
+			keyframes.push(CameraKeyframe {
+				time,
+				position: Vec3::new(px, py, pz),
+				target: Vec3::new(tx, ty, tz),
+				fov,
+				focus_dist,
+			});
+		}
+		Self::new(keyframes)
+	}
+
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+		let description = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+		Self::parse(&description)
+	}
+
+	pub fn keyframes(&self) -> &[CameraKeyframe] {
+		&self.keyframes
+	}
+
+	pub fn start_time(&self) -> Scalar {
+		self.keyframes[0].time
+	}
+
+	pub fn end_time(&self) -> Scalar {
+		self.keyframes[self.keyframes.len() - 1].time
+	}
+
+	// interpolated state, times outside of the track hold the first or last keyframe
+	pub fn sample(&self, time: Scalar) -> CameraKeyframe {
+		let keys = &self.keyframes;
+		let segment = keys.partition_point(|key| key.time <= time);
+		if segment == 0 {
+			return CameraKeyframe { time, ..keys[0] };
+		}
+		if segment == keys.len() {
+			return CameraKeyframe {
+				time,
+				..keys[keys.len() - 1]
+			};
+		}
+		// the segment and its neighbors, repeated at the ends of the track
+		let segment_keys = [
+			&keys[segment.saturating_sub(2)],
+			&keys[segment - 1],
+			&keys[segment],
+			&keys[(segment + 1).min(keys.len() - 1)],
+		];
+		let t = (time - segment_keys[1].time) / (segment_keys[2].time - segment_keys[1].time);
+		CameraKeyframe {
+			time,
+			position: smooth_segment(segment_keys.map(|key| (key.time, key.position)), t),
+			target: smooth_segment(segment_keys.map(|key| (key.time, key.target)), t),
+			fov: smooth_segment(segment_keys.map(|key| (key.time, key.fov)), t).max(1e-3),
+			focus_dist: smooth_segment(segment_keys.map(|key| (key.time, key.focus_dist)), t)
+				.max(1e-6),
+		}
+	}
+
+	pub fn camera(
+		&self,
+		time: Scalar,
+		defocus_angle: Scalar,
+		width: usize,
+		height: usize,
+	) -> Camera {
+		let key = self.sample(time);
+		Camera::look_at(
+			key.position,
+			key.target,
+			Camera::WORLD_UP,
+			key.fov,
+			defocus_angle,
+			width,
+			height,
+		)
+		.with_focus_dist(key.focus_dist)
+	}
+}
+
+// Values that can be interpolated between keyframes
+pub trait Animatable:
+	Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Scalar, Output = Self>
+{
+}
+
+impl<T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Scalar, Output = T>> Animatable for T {}
+
+// How a value gets from a keyframe to the next one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+	#[default]
+	Linear,
+	// smooth curve with automatically placed handles (a third of the catmull-rom tangent), so the
+	// value eases through the keyframe instead of changing direction abruptly
+	Bezier,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe<T> {
+	// in seconds
+	pub time: Scalar,
+	pub value: T,
+	// used for the segment that starts at this keyframe
+	pub interpolation: Interpolation,
+}
+
+impl<T> Keyframe<T> {
+	pub fn linear(time: Scalar, value: T) -> Self {
+		Self {
+			time,
+			value,
+			interpolation: Interpolation::Linear,
+		}
+	}
+
+	pub fn bezier(time: Scalar, value: T) -> Self {
+		Self {
+			time,
+			value,
+			interpolation: Interpolation::Bezier,
+		}
+	}
+}
+
+// Keyframed value, times outside of the track hold the first or last keyframe
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T> {
+	// sorted by time
+	keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Animatable> Track<T> {
+	pub fn new(mut keyframes: Vec<Keyframe<T>>) -> Result<Self, String> {
+		if keyframes.is_empty() {
+			return Err("track has no keyframes".to_string());
+		}
+		keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+		if keyframes
+			.windows(2)
+			.any(|pair| pair[0].time == pair[1].time)
+		{
+			return Err("track has two keyframes at the same time".to_string());
+		}
+		Ok(Self { keyframes })
+	}
+
+	pub fn keyframes(&self) -> &[Keyframe<T>] {
+		&self.keyframes
+	}
+
+	pub fn end_time(&self) -> Scalar {
+		self.keyframes[self.keyframes.len() - 1].time
+	}
+
+	pub fn sample(&self, time: Scalar) -> T {
+		let keys = &self.keyframes;
+		let segment = keys.partition_point(|key| key.time <= time);
+		if segment == 0 {
+			return keys[0].value;
+		}
+		if segment == keys.len() {
+			return keys[keys.len() - 1].value;
+		}
+		let (start, end) = (&keys[segment - 1], &keys[segment]);
+		let t = (time - start.time) / (end.time - start.time);
+		match start.interpolation {
+			Interpolation::Linear => start.value * (1.0 - t) + end.value * t,
+			Interpolation::Bezier => smooth_segment(
+				[
+					&keys[segment.saturating_sub(2)],
+					start,
+					end,
+					&keys[(segment + 1).min(keys.len() - 1)],
+				]
+				.map(|key| (key.time, key.value)),
+				t,
+			),
+		}
+	}
+}
+
+// Translation (relative to where the object is in the scene) and uniform scale (around the center
+// of the object) over time together with its material parameters, each of them is optional.
+// Shapes are axis aligned, so objects can't rotate. Material parameters are set on every material
+// of the object that has them (e.g. albedo on lambertian, metal and subsurface materials).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectAnimation {
+	// index of the object in the scene
+	pub object_id: usize,
+	pub translation: Option<Track<Vec3>>,
+	pub scale: Option<Track<Scalar>>,
+	pub albedo: Option<Track<Vec3>>,
+	pub emission: Option<Track<Scalar>>,
+	pub fuzz: Option<Track<Scalar>>,
+	pub ir: Option<Track<Scalar>>,
+}
+
+impl ObjectAnimation {
+	pub fn new(object_id: usize) -> Self {
+		Self {
+			object_id,
+			translation: None,
+			scale: None,
+			albedo: None,
+			emission: None,
+			fuzz: None,
+			ir: None,
+		}
+	}
+
+	pub fn with_translation(self, translation: Track<Vec3>) -> Self {
+		Self {
+			translation: Some(translation),
+			..self
+		}
+	}
+
+	pub fn with_scale(self, scale: Track<Scalar>) -> Self {
+		Self {
+			scale: Some(scale),
+			..self
+		}
+	}
+
+	pub fn with_albedo(self, albedo: Track<Vec3>) -> Self {
+		Self {
+			albedo: Some(albedo),
+			..self
+		}
+	}
+
+	pub fn with_emission(self, emission: Track<Scalar>) -> Self {
+		Self {
+			emission: Some(emission),
+			..self
+		}
+	}
+
+	pub fn with_fuzz(self, fuzz: Track<Scalar>) -> Self {
+		Self {
+			fuzz: Some(fuzz),
+			..self
+		}
+	}
+
+	pub fn with_ir(self, ir: Track<Scalar>) -> Self {
+		Self {
+			ir: Some(ir),
+			..self
+		}
+	}
+
+	// time of the last keyframe of all tracks
+	pub fn end_time(&self) -> Scalar {
+		[
+			self.translation.as_ref().map(Track::end_time),
+			self.scale.as_ref().map(Track::end_time),
+			self.albedo.as_ref().map(Track::end_time),
+			self.emission.as_ref().map(Track::end_time),
+			self.fuzz.as_ref().map(Track::end_time),
+			self.ir.as_ref().map(Track::end_time),
+		]
+		.into_iter()
+		.flatten()
+		.fold(0.0, Scalar::max)
+	}
+
+	fn apply(&self, object: &Object, time: Scalar, shutter_time: Scalar) -> Object {
+		let pivot = object.get_aabb().center();
+		let translation = self
+			.translation
+			.as_ref()
+			.map_or(Vec3::zero(), |track| track.sample(time));
+		let scale = self
+			.scale
+			.as_ref()
+			.map_or(1.0, |track| track.sample(time).max(0.0));
+		let mut object = transform_object(object, pivot, translation, scale);
+		for_each_material(&mut object, &mut |material| {
+			self.animate_material(material, time)
+		});
+
+		// only the translation is blurred, linearly over the time the shutter is open
+		match &self.translation {
+			Some(track) if shutter_time > 0.0 => {
+				let velocity = (track.sample(time + shutter_time) - translation) / shutter_time;
+				if velocity.near_zero() {
+					object
+				} else {
+					Object::Moving(Box::new(Moving::new(object, velocity, shutter_time)))
+				}
+			}
+			_ => object,
+		}
+	}
+
+	fn animate_material(&self, material: &mut Material, time: Scalar) {
+		let sample = |track: &Option<Track<Scalar>>, value: &mut Scalar| {
+			if let Some(track) = track {
+				*value = track.sample(time);
+			}
+		};
+		let sample_albedo = |value: &mut Vec3| {
+			if let Some(track) = &self.albedo {
+				*value = track.sample(time).clamp(0.0, 1.0);
+			}
+		};
+		match material {
+			Material::Lambertain { albedo, emission } => {
+				sample_albedo(albedo);
+				sample(&self.emission, emission);
+			}
+			Material::Metal { albedo, fuzz } => {
+				sample_albedo(albedo);
+				sample(&self.fuzz, fuzz);
+			}
+			Material::Dielectric { ir } => sample(&self.ir, ir),
+			Material::Subsurface { albedo, ir, .. } => {
+				sample_albedo(albedo);
+				sample(&self.ir, ir);
+			}
+			Material::DispersiveDielectric { .. } => {}
+			Material::ThinFilm { base, .. }
+			| Material::Clearcoat { base, .. }
+			| Material::NormalMap { base, .. }
+			| Material::BumpMap { base, .. }
+			| Material::Cutout { base, .. } => self.animate_material(base, time),
+			Material::Mix { first, second, .. } => {
+				self.animate_material(first, time);
+				self.animate_material(second, time);
+			}
+			Material::Layered { coat, base } => {
+				self.animate_material(coat, time);
+				self.animate_material(base, time);
+			}
+		}
+	}
+}
+
+// Scene with animated objects, evaluated into a static scene (and BVH) for every frame
+#[derive(Debug)]
+pub struct AnimatedScene {
+	pub scene: Scene,
+	pub animations: Vec<ObjectAnimation>,
+}
+
+impl AnimatedScene {
+	pub fn new(scene: Scene) -> Self {
+		Self {
+			scene,
+			animations: Vec::new(),
+		}
+	}
+
+	pub fn with_animation(mut self, animation: ObjectAnimation) -> Self {
+		self.animations.push(animation);
+		self
+	}
+
+	// time of the last keyframe, 0 for static scenes
+	pub fn duration(&self) -> Scalar {
+		self.animations
+			.iter()
+			.map(ObjectAnimation::end_time)
+			.fold(0.0, Scalar::max)
+	}
+
+	// the scene at time, objects that move while the shutter is open (rays with a time between 0
+	// and shutter_time) are motion blurred
+	pub fn scene_at(&self, time: Scalar, shutter_time: Scalar) -> Scene {
+		let mut objects = self.scene.objects.clone();
+		for animation in &self.animations {
+			if let Some(object) = objects.get_mut(animation.object_id) {
+				*object = animation.apply(object, time, shutter_time);
+			}
+		}
+		Scene::new(objects)
+	}
+
+	pub fn bvh_at(&self, time: Scalar, shutter_time: Scalar) -> Option<BVH> {
+		BVH::new(self.scene_at(time, shutter_time))
+	}
+
+	// four second loop with every kind of track: a sliding metal sphere that gets rough, a glass
+	// sphere changing its ior, a growing sphere changing color, a pulsing light and a fast cube
+	pub fn create_sample_animation() -> Self {
+		let mut scene = Scene::new(Vec::new());
+		scene.add_cube(
+			Vec3::new(0.0, -1000.0, 0.0),
+			Vec3::uniform(1000.0),
+			Material::Lambertain {
+				albedo: Vec3::new(0.5, 0.5, 0.5),
+				emission: 0.0,
+			},
+		);
+		scene.add_sphere(
+			Vec3::new(0.0, 1.0, -3.0),
+			1.0,
+			Material::Metal {
+				albedo: Vec3::new(0.8, 0.8, 0.85),
+				fuzz: 0.0,
+			},
+		);
+		scene.add_sphere(
+			Vec3::new(0.0, 1.0, 0.0),
+			1.0,
+			Material::Dielectric { ir: 1.5 },
+		);
+		scene.add_sphere(
+			Vec3::new(0.0, 1.0, 3.0),
+			1.0,
+			Material::Lambertain {
+				albedo: Vec3::new(0.8, 0.1, 0.1),
+				emission: 0.0,
+			},
+		);
+		scene.add_sphere(
+			Vec3::new(-4.0, 3.5, 0.0),
+			0.5,
+			Material::Lambertain {
+				albedo: Vec3::one(),
+				emission: 0.0,
+			},
+		);
+		scene.add_cube(
+			Vec3::new(3.0, 0.3, -6.0),
+			Vec3::uniform(0.3),
+			Material::Lambertain {
+				albedo: Vec3::new(0.2, 0.4, 0.9),
+				emission: 0.0,
+			},
+		);
+
+		fn track<T: Animatable>(keyframes: Vec<Keyframe<T>>) -> Track<T> {
+			Track::new(keyframes).unwrap()
+		}
+		Self::new(scene)
+			.with_animation(
+				ObjectAnimation::new(1)
+					.with_translation(track(vec![
+						Keyframe::bezier(0.0, Vec3::new(0.0, 0.0, -2.0)),
+						Keyframe::bezier(2.0, Vec3::new(0.0, 0.0, 2.0)),
+						Keyframe::bezier(4.0, Vec3::new(0.0, 0.0, -2.0)),
+					]))
+					.with_fuzz(track(vec![
+						Keyframe::linear(0.0, 0.0),
+						Keyframe::linear(2.0, 0.5),
+						Keyframe::linear(4.0, 0.0),
+					])),
+			)
+			.with_animation(ObjectAnimation::new(2).with_ir(track(vec![
+				Keyframe::bezier(0.0, 1.0),
+				Keyframe::bezier(2.0, 1.8),
+				Keyframe::bezier(4.0, 1.0),
+			])))
+			.with_animation(
+				ObjectAnimation::new(3)
+					.with_scale(track(vec![
+						Keyframe::bezier(0.0, 1.0),
+						Keyframe::bezier(2.0, 1.5),
+						Keyframe::bezier(4.0, 1.0),
+					]))
+					.with_albedo(track(vec![
+						Keyframe::linear(0.0, Vec3::new(0.8, 0.1, 0.1)),
+						Keyframe::linear(1.3, Vec3::new(0.1, 0.8, 0.1)),
+						Keyframe::linear(2.7, Vec3::new(0.1, 0.1, 0.8)),
+						Keyframe::linear(4.0, Vec3::new(0.8, 0.1, 0.1)),
+					])),
+			)
+			.with_animation(ObjectAnimation::new(4).with_emission(track(vec![
+				Keyframe::linear(0.0, 0.0),
+				Keyframe::linear(1.0, 20.0),
+				Keyframe::linear(2.0, 0.0),
+				Keyframe::linear(3.0, 20.0),
+				Keyframe::linear(4.0, 0.0),
+			])))
+			.with_animation(ObjectAnimation::new(5).with_translation(track(vec![
+				Keyframe::linear(0.0, Vec3::zero()),
+				Keyframe::linear(1.0, Vec3::new(-12.0, 0.0, 0.0)),
+				Keyframe::linear(2.0, Vec3::zero()),
+				Keyframe::linear(3.0, Vec3::new(-12.0, 0.0, 0.0)),
+				Keyframe::linear(4.0, Vec3::zero()),
+			])))
+	}
+}
+
+// moves the object by translation and scales it around pivot
+fn transform_object(object: &Object, pivot: Vec3, translation: Vec3, scale: Scalar) -> Object {
+	let position = |p: Vec3| pivot + (p - pivot) * scale + translation;
+	match object {
+		Object::Sphere(sphere) => Object::Sphere(Sphere::new(
+			position(sphere.center),
+			sphere.radius * scale,
+			sphere.material.clone(),
+		)),
+		Object::Cube(cube) => Object::Cube(Cube::new(
+			position(cube.center),
+			cube.half_extend * scale,
+			cube.material.clone(),
+		)),
+		Object::Csg(csg) => Object::Csg(Box::new(Csg::new(
+			csg.operation,
+			transform_object(&csg.left, pivot, translation, scale),
+			transform_object(&csg.right, pivot, translation, scale),
+		))),
+		Object::Moving(moving) => Object::Moving(Box::new(Moving::new(
+			transform_object(&moving.object, pivot, translation, scale),
+			moving.velocity,
+			moving.duration,
+		))),
+	}
+}
+
+fn for_each_material(object: &mut Object, f: &mut impl FnMut(&mut Material)) {
+	match object {
+		Object::Sphere(sphere) => f(&mut sphere.material),
+		Object::Cube(cube) => f(&mut cube.material),
+		Object::Csg(csg) => {
+			for_each_material(&mut csg.left, f);
+			for_each_material(&mut csg.right, f);
+		}
+		Object::Moving(moving) => for_each_material(&mut moving.object, f),
+	}
+}
+
+// cubic bezier segment between the middle two keys, the handles are a third of the catmull-rom
+// tangents for unevenly spaced keyframes, at the ends of a track (a repeated key) the tangent is the
+// slope of the segment
+fn smooth_segment<T: Animatable>(keys: [(Scalar, T); 4], t: Scalar) -> T {
+	let [(t0, p0), (t1, p1), (t2, p2), (t3, p3)] = keys;
+	let slope = |(ta, a): (Scalar, T), (tb, b): (Scalar, T)| (b - a) * (1.0 / (tb - ta));
+	let m1 = slope(if t0 < t1 { (t0, p0) } else { (t1, p1) }, (t2, p2));
+	let m2 = slope((t1, p1), if t2 < t3 { (t3, p3) } else { (t2, p2) });
+
+	let dt = t2 - t1;
+	let c1 = p1 + m1 * (dt / 3.0);
+	let c2 = p2 - m2 * (dt / 3.0);
+	let s = 1.0 - t;
+	p1 * (s * s * s) + c1 * (3.0 * s * s * t) + c2 * (3.0 * s * t * t) + p2 * (t * t * t)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_close(a: Scalar, b: Scalar) {
+		assert!((a - b).abs() < 1e-9, "{a} != {b}");
+	}
+
+	#[test]
+	fn sample_hits_the_keyframes() {
+		for keyframe in [Keyframe::linear, Keyframe::bezier] {
+			let track = Track::new(vec![
+				keyframe(0.0, 1.0),
+				keyframe(1.0, 3.0),
+				keyframe(3.0, -2.0),
+			])
+			.unwrap();
+			assert_close(track.sample(0.0), 1.0);
+			assert_close(track.sample(1.0), 3.0);
+			assert_close(track.sample(3.0), -2.0);
+		}
+	}
+
+	#[test]
+	fn sample_holds_the_ends() {
+		let track =
+			Track::new(vec![Keyframe::linear(1.0, 2.0), Keyframe::linear(2.0, 4.0)]).unwrap();
+		assert_close(track.sample(-5.0), 2.0);
+		assert_close(track.sample(10.0), 4.0);
+
+		let track = Track::new(vec![Keyframe::bezier(1.0, 7.0)]).unwrap();
+		assert_close(track.sample(0.0), 7.0);
+		assert_close(track.sample(1.5), 7.0);
+	}
+
+	#[test]
+	fn linear_sample_interpolates_between_keyframes() {
+		let track = Track::new(vec![
+			Keyframe::linear(0.0, Vec3::zero()),
+			Keyframe::linear(2.0, Vec3::new(2.0, 4.0, -6.0)),
+		])
+		.unwrap();
+		assert_eq!(track.sample(0.5), Vec3::new(0.5, 1.0, -1.5));
+		assert_eq!(track.sample(1.0), Vec3::new(1.0, 2.0, -3.0));
+	}
+
+	#[test]
+	fn bezier_sample_eases_through_keyframes() {
+		// evenly spaced keyframes on a line stay on the line
+		let track = Track::new(vec![
+			Keyframe::bezier(0.0, 0.0),
+			Keyframe::bezier(1.0, 1.0),
+			Keyframe::bezier(2.0, 2.0),
+		])
+		.unwrap();
+		assert_close(track.sample(0.25), 0.25);
+		assert_close(track.sample(1.5), 1.5);
+
+		// a peak is approached with a flat tangent, so the curve overshoots the straight line
+		let track = Track::new(vec![
+			Keyframe::bezier(0.0, 0.0),
+			Keyframe::bezier(1.0, 1.0),
+			Keyframe::bezier(2.0, 0.0),
+		])
+		.unwrap();
+		assert_close(track.sample(0.5), 0.625);
+		assert_close(track.sample(1.5), 0.625);
+	}
+
+	#[test]
+	fn interpolation_is_taken_from_the_segment_start() {
+		let track = Track::new(vec![
+			Keyframe::linear(0.0, 0.0),
+			Keyframe::bezier(1.0, 1.0),
+			Keyframe::linear(2.0, 0.0),
+		])
+		.unwrap();
+		assert_close(track.sample(0.5), 0.5);
+		assert!(track.sample(1.5) > 0.5);
+	}
+
+	#[test]
+	fn new_sorts_and_validates_keyframes() {
+		let track =
+			Track::new(vec![Keyframe::linear(2.0, 4.0), Keyframe::linear(0.0, 0.0)]).unwrap();
+		assert_eq!(track.end_time(), 2.0);
+		assert_close(track.sample(1.0), 2.0);
+
+		assert!(Track::<Scalar>::new(vec![]).is_err());
+		assert!(Track::new(vec![Keyframe::linear(1.0, 0.0), Keyframe::linear(1.0, 1.0)]).is_err());
+	}
+}
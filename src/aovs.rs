@@ -0,0 +1,341 @@
+use crate::{
+	Scalar, Vec3,
+	checkpoint::{
+		read_scalar, read_u8, read_u32, read_u64, read_vec3, write_scalar, write_u8, write_u32,
+		write_u64, write_vec3,
+	},
+};
+use std::io::{self, Read, Write};
+
+// Geometric information about the first surface a camera ray hit
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceAovs {
+	// distance from the camera
+	pub depth: Scalar,
+	pub position: Vec3,
+	pub normal: Vec3,
+	pub object_id: usize,
+	pub material_id: u64,
+}
+
+// Arbitrary output variables of a single sample, all colors are in linear color space
+#[derive(Debug, Clone, Copy)]
+pub struct Aovs {
+	// None if the camera ray hit the sky
+	pub surface: Option<SurfaceAovs>,
+	// sky color if the camera ray hit the sky
+	pub albedo: Vec3,
+	// light emitted by the first hit or the sky seen directly by the camera
+	pub emission: Vec3,
+	// light arriving at the first hit directly from an emitter or the sky
+	pub direct: Vec3,
+	// light arriving at the first hit after bouncing at least once more
+	pub indirect: Vec3,
+}
+
+impl Aovs {
+	pub fn new() -> Self {
+		Self {
+			surface: None,
+			albedo: Vec3::zero(),
+			emission: Vec3::zero(),
+			direct: Vec3::zero(),
+			indirect: Vec3::zero(),
+		}
+	}
+
+	// sorts light by the bounce it was gathered at
+	#[inline(always)]
+	pub fn add_light(&mut self, bounce: u32, light: Vec3) {
+		match bounce {
+			0 => self.emission = self.emission + light,
+			1 => self.direct = self.direct + light,
+			_ => self.indirect = self.indirect + light,
+		}
+	}
+
+	// applies the exposure of the film to the light aovs
+	pub fn scale_light(&mut self, scale: Vec3) {
+		self.emission = self.emission * scale;
+		self.direct = self.direct * scale;
+		self.indirect = self.indirect * scale;
+	}
+}
+
+impl Default for Aovs {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AovLayer {
+	Depth,
+	Position,
+	Normal,
+	Albedo,
+	ObjectId,
+	MaterialId,
+	Direct,
+	Indirect,
+	Emission,
+	SampleCount,
+}
+
+impl AovLayer {
+	pub const ALL: [AovLayer; 10] = [
+		AovLayer::Depth,
+		AovLayer::Position,
+		AovLayer::Normal,
+		AovLayer::Albedo,
+		AovLayer::ObjectId,
+		AovLayer::MaterialId,
+		AovLayer::Direct,
+		AovLayer::Indirect,
+		AovLayer::Emission,
+		AovLayer::SampleCount,
+	];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			AovLayer::Depth => "depth",
+			AovLayer::Position => "position",
+			AovLayer::Normal => "normal",
+			AovLayer::Albedo => "albedo",
+			AovLayer::ObjectId => "object_id",
+			AovLayer::MaterialId => "material_id",
+			AovLayer::Direct => "direct",
+			AovLayer::Indirect => "indirect",
+			AovLayer::Emission => "emission",
+			AovLayer::SampleCount => "sample_count",
+		}
+	}
+}
+
+// Accumulates the aovs of all samples of one pixel
+#[derive(Debug, Clone, Copy)]
+pub struct AovPixel {
+	depth: Scalar,
+	position: Vec3,
+	normal: Vec3,
+	albedo: Vec3,
+	emission: Vec3,
+	direct: Vec3,
+	indirect: Vec3,
+	// ids of the first sample that hit a surface, ids can't be averaged
+	object_id: Option<usize>,
+	material_id: Option<u64>,
+	hit_count: u32,
+	sample_count: u32,
+}
+
+impl AovPixel {
+	pub fn new() -> Self {
+		Self {
+			depth: 0.0,
+			position: Vec3::zero(),
+			normal: Vec3::zero(),
+			albedo: Vec3::zero(),
+			emission: Vec3::zero(),
+			direct: Vec3::zero(),
+			indirect: Vec3::zero(),
+			object_id: None,
+			material_id: None,
+			hit_count: 0,
+			sample_count: 0,
+		}
+	}
+
+	pub fn add_sample(&mut self, aovs: &Aovs) {
+		if let Some(surface) = &aovs.surface {
+			self.depth += surface.depth;
+			self.position = self.position + surface.position;
+			self.normal = self.normal + surface.normal;
+			self.object_id.get_or_insert(surface.object_id);
+			self.material_id.get_or_insert(surface.material_id);
+			self.hit_count += 1;
+		}
+		self.albedo = self.albedo + aovs.albedo;
+		self.emission = self.emission + aovs.emission;
+		self.direct = self.direct + aovs.direct;
+		self.indirect = self.indirect + aovs.indirect;
+		self.sample_count += 1;
+	}
+
+	pub fn sample_count(&self) -> u32 {
+		self.sample_count
+	}
+
+	pub(crate) fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+		write_scalar(writer, self.depth)?;
+		for value in [
+			self.position,
+			self.normal,
+			self.albedo,
+			self.emission,
+			self.direct,
+			self.indirect,
+		] {
+			write_vec3(writer, value)?;
+		}
+		write_u8(writer, self.object_id.is_some() as u8)?;
+		write_u64(writer, self.object_id.unwrap_or_default() as u64)?;
+		write_u8(writer, self.material_id.is_some() as u8)?;
+		write_u64(writer, self.material_id.unwrap_or_default())?;
+		write_u32(writer, self.hit_count)?;
+		write_u32(writer, self.sample_count)
+	}
+
+	pub(crate) fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+		let depth = read_scalar(reader)?;
+		let position = read_vec3(reader)?;
+		let normal = read_vec3(reader)?;
+		let albedo = read_vec3(reader)?;
+		let emission = read_vec3(reader)?;
+		let direct = read_vec3(reader)?;
+		let indirect = read_vec3(reader)?;
+		let has_object_id = read_u8(reader)? != 0;
+		let object_id = read_u64(reader)? as usize;
+		let has_material_id = read_u8(reader)? != 0;
+		let material_id = read_u64(reader)?;
+		Ok(Self {
+			depth,
+			position,
+			normal,
+			albedo,
+			emission,
+			direct,
+			indirect,
+			object_id: has_object_id.then_some(object_id),
+			material_id: has_material_id.then_some(material_id),
+			hit_count: read_u32(reader)?,
+			sample_count: read_u32(reader)?,
+		})
+	}
+
+	// infinite if no sample hit a surface
+	pub fn depth(&self) -> Scalar {
+		if self.hit_count == 0 {
+			Scalar::INFINITY
+		} else {
+			self.depth / self.hit_count as Scalar
+		}
+	}
+
+	pub fn position(&self) -> Vec3 {
+		self.average_over_hits(self.position)
+	}
+
+	pub fn normal(&self) -> Vec3 {
+		let normal = self.average_over_hits(self.normal);
+		if normal.near_zero() {
+			normal
+		} else {
+			normal.normalize()
+		}
+	}
+
+	pub fn albedo(&self) -> Vec3 {
+		self.average_over_samples(self.albedo)
+	}
+
+	pub fn emission(&self) -> Vec3 {
+		self.average_over_samples(self.emission)
+	}
+
+	pub fn direct(&self) -> Vec3 {
+		self.average_over_samples(self.direct)
+	}
+
+	pub fn indirect(&self) -> Vec3 {
+		self.average_over_samples(self.indirect)
+	}
+
+	pub fn object_id(&self) -> Option<usize> {
+		self.object_id
+	}
+
+	pub fn material_id(&self) -> Option<u64> {
+		self.material_id
+	}
+
+	// ids are visualized as random but stable colors, black if nothing was hit
+	pub fn get_layer(&self, layer: AovLayer) -> Vec3 {
+		match layer {
+			AovLayer::Depth => Vec3::uniform(self.depth()),
+			AovLayer::Position => self.position(),
+			AovLayer::Normal => self.normal(),
+			AovLayer::Albedo => self.albedo(),
+			AovLayer::ObjectId => self
+				.object_id
+				.map(|id| id_to_color(id as u64))
+				.unwrap_or(Vec3::zero()),
+			AovLayer::MaterialId => self.material_id.map(id_to_color).unwrap_or(Vec3::zero()),
+			AovLayer::Direct => self.direct(),
+			AovLayer::Indirect => self.indirect(),
+			AovLayer::Emission => self.emission(),
+			AovLayer::SampleCount => Vec3::uniform(self.sample_count as Scalar),
+		}
+	}
+
+	#[inline(always)]
+	fn average_over_hits(&self, sum: Vec3) -> Vec3 {
+		if self.hit_count == 0 {
+			Vec3::zero()
+		} else {
+			sum / self.hit_count as Scalar
+		}
+	}
+
+	#[inline(always)]
+	fn average_over_samples(&self, sum: Vec3) -> Vec3 {
+		if self.sample_count == 0 {
+			Vec3::zero()
+		} else {
+			sum / self.sample_count as Scalar
+		}
+	}
+}
+
+impl Default for AovPixel {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct AovBuffer {
+	pub width: usize,
+	pub height: usize,
+	pub pixels: Vec<AovPixel>,
+}
+
+impl AovBuffer {
+	pub fn new(width: usize, height: usize) -> Self {
+		Self {
+			width,
+			height,
+			pixels: vec![AovPixel::new(); width * height],
+		}
+	}
+
+	pub fn get_layer_image(&self, layer: AovLayer) -> image::Rgb32FImage {
+		image::Rgb32FImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+			let value = self.pixels[y as usize * self.width + x as usize].get_layer(layer);
+			image::Rgb([value.x as f32, value.y as f32, value.z as f32])
+		})
+	}
+}
+
+// splitmix64 finalizer, spreads neighbouring ids to very different colors
+fn id_to_color(id: u64) -> Vec3 {
+	let mut z = id.wrapping_add(0x9E3779B97F4A7C15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+	z ^= z >> 31;
+	Vec3::new(
+		(z & 0xFF) as Scalar / 255.0,
+		((z >> 8) & 0xFF) as Scalar / 255.0,
+		((z >> 16) & 0xFF) as Scalar / 255.0,
+	)
+}
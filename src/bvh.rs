@@ -1,10 +1,17 @@
-use crate::{AABB, Bounded, Object, Ray, RayHit, Renderable, Scalar, Scene};
+use crate::{
+	AABB, Bounded, Hit, Hittable, Object, Ray, RayHit, Renderable, Sampler, Scalar, Scene,
+};
 use std::cmp::Ordering;
 
+// after passing through this many transparent surfaces the next surface a ray hits is treated as
+// opaque, so the ray counts as blocked instead of reaching the sky
+const MAX_TRANSPARENT_HITS: u32 = 64;
+
 #[derive(Debug)]
 enum BVHNode {
 	Branch { left: Box<BVH>, right: Box<BVH> },
-	FewObjects(Scene),
+	// objects are stored with their index in the original scene as id
+	FewObjects(Vec<(usize, Object)>),
 }
 
 #[derive(Debug)]
@@ -14,9 +21,13 @@ pub struct BVH {
 }
 
 impl BVH {
-	pub fn new(mut scene: Scene) -> Option<Self> {
-		fn box_compare(axis: usize) -> impl FnMut(&Object, &Object) -> Ordering {
-			move |a, b| {
+	pub fn new(scene: Scene) -> Option<Self> {
+		Self::build(scene.objects.into_iter().enumerate().collect())
+	}
+
+	fn build(mut objects: Vec<(usize, Object)>) -> Option<Self> {
+		fn box_compare(axis: usize) -> impl FnMut(&(usize, Object), &(usize, Object)) -> Ordering {
+			move |(_, a), (_, b)| {
 				let a_aabb = a.get_aabb();
 				let b_aabb = b.get_aabb();
 				let ac = a_aabb.min[axis] + a_aabb.max[axis];
@@ -25,11 +36,11 @@ impl BVH {
 			}
 		}
 
-		fn axis_range(objects: &[Object], axis: usize) -> Scalar {
+		fn axis_range(objects: &[(usize, Object)], axis: usize) -> Scalar {
 			let (min, max) =
 				objects
 					.iter()
-					.fold((Scalar::MAX, Scalar::MIN), |(bmin, bmax), hit| {
+					.fold((Scalar::MAX, Scalar::MIN), |(bmin, bmax), (_, hit)| {
 						let aabb = hit.get_aabb();
 						(bmin.min(aabb.min[axis]), bmax.max(aabb.max[axis]))
 					});
@@ -37,33 +48,31 @@ impl BVH {
 		}
 
 		let mut axis_ranges: [(usize, Scalar); 3] = [
-			(0, axis_range(&scene.objects, 0)),
-			(1, axis_range(&scene.objects, 1)),
-			(2, axis_range(&scene.objects, 2)),
+			(0, axis_range(&objects, 0)),
+			(1, axis_range(&objects, 1)),
+			(2, axis_range(&objects, 2)),
 		];
 
 		axis_ranges.sort_unstable_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
 		let axis = axis_ranges[0].0;
 
-		scene.objects.sort_unstable_by(box_compare(axis));
-		match scene.objects.len() {
+		objects.sort_unstable_by(box_compare(axis));
+		match objects.len() {
 			0 => None,
 			1..6 => {
-				let mut aabb = scene.objects[0].get_aabb();
-				for i in 1..scene.objects.len() {
-					aabb = AABB::surrounding(aabb, scene.objects[i].get_aabb());
+				let mut aabb = objects[0].1.get_aabb();
+				for (_, object) in objects.iter().skip(1) {
+					aabb = AABB::surrounding(aabb, object.get_aabb());
 				}
 				Some(BVH {
-					tree: BVHNode::FewObjects(scene),
+					tree: BVHNode::FewObjects(objects),
 					aabb,
 				})
 			}
 			_ => {
-				let right = BVH::new(Scene::new(
-					scene.objects.drain(scene.objects.len() / 2..).collect(),
-				));
-				let left = BVH::new(scene);
+				let right = BVH::build(objects.drain(objects.len() / 2..).collect());
+				let left = BVH::build(objects);
 				if let (Some(left), Some(right)) = (left, right) {
 					let aabb = AABB::surrounding(left.aabb, right.aabb);
 					Some(BVH {
@@ -81,38 +90,43 @@ impl BVH {
 	}
 
 	#[inline(always)]
-	fn hit(&self, ray: &Ray) -> Option<(Scalar, &Object)> {
+	fn hit(&self, ray: &Ray) -> Option<(Hit<'_>, usize, &Object)> {
 		if self.aabb.hit(ray) {
 			let is_distance_valid = |distance: Scalar| distance > 0.001;
 
 			match &self.tree {
-				BVHNode::FewObjects(scene) => scene.hit(ray),
+				BVHNode::FewObjects(objects) => {
+					let mut closest: Option<(Hit, usize, &Object)> = None;
+					for (id, object) in objects.iter() {
+						if let Some(hit) = object.hit(ray)
+							&& is_distance_valid(hit.distance)
+							&& closest
+								.as_ref()
+								.is_none_or(|(closest, _, _)| hit.distance < closest.distance)
+						{
+							closest = Some((hit, *id, object));
+						}
+					}
+					closest
+				}
 				BVHNode::Branch { left, right } => {
 					let left = left.hit(ray);
 					let right = right.hit(ray);
 					match (left, right) {
-						(
-							Some((left_distance, left_object)),
-							Some((right_distance, right_object)),
-						) => {
-							if is_distance_valid(left_distance) && left_distance < right_distance {
-								Some((left_distance, left_object))
-							} else if is_distance_valid(right_distance) {
-								Some((right_distance, right_object))
-							} else {
-								None
-							}
-						}
-						(Some((distance, object)), None) => {
-							if is_distance_valid(distance) {
-								Some((distance, object))
+						(Some(left), Some(right)) => {
+							if is_distance_valid(left.0.distance)
+								&& left.0.distance < right.0.distance
+							{
+								Some(left)
+							} else if is_distance_valid(right.0.distance) {
+								Some(right)
 							} else {
 								None
 							}
 						}
-						(None, Some((distance, object))) => {
-							if is_distance_valid(distance) {
-								Some((distance, object))
+						(Some(hit), None) | (None, Some(hit)) => {
+							if is_distance_valid(hit.0.distance) {
+								Some(hit)
 							} else {
 								None
 							}
@@ -126,18 +140,44 @@ impl BVH {
 		}
 	}
 
+	// closest hit that isn't cut out by the opacity of its material, fractional opacity lets that
+	// fraction of rays through, decided with the next random number of the sampler
 	#[inline(always)]
-	pub fn trace(&self, ray: &Ray) -> Option<RayHit<'_>> {
-		self.hit(ray).and_then(|(distance, object)| {
-			object.get_material().map(|material| {
-				let p = ray.at(distance);
-				let mut normal = object.get_normal(&p, ray);
-				let front_face = ray.dir.dot(normal) < 0.0;
-				if !front_face {
-					normal = -normal;
-				}
-				RayHit::new(p, normal, material, front_face)
-			})
-		})
+	pub fn trace(&self, ray: &Ray, sampler: &mut dyn Sampler) -> Option<RayHit<'_>> {
+		let mut current = *ray;
+		let mut transparent_hits = 0;
+		loop {
+			let (hit, id, object) = self.hit(&current)?;
+			let p = current.at(hit.distance);
+			let (material, (uv, tangent)) = match &hit.crossing {
+				Some(crossing) => (crossing.material, crossing.get_surface_frame(&p, &current)),
+				None => (
+					object.get_material(&p, &current)?,
+					object.get_surface_frame(&p, &current),
+				),
+			};
+
+			let opacity = material.opacity(uv);
+			if opacity < 1.0
+				&& transparent_hits < MAX_TRANSPARENT_HITS
+				&& sampler.get_1d() >= opacity
+			{
+				transparent_hits += 1;
+				current = Ray::new(p, ray.dir).with_time(ray.time);
+				continue;
+			}
+
+			let mut normal = match &hit.crossing {
+				Some(crossing) => crossing.normal,
+				None => object.get_normal(&p, &current),
+			};
+			let front_face = ray.dir.dot(normal) < 0.0;
+			if !front_face {
+				normal = -normal;
+			}
+			return Some(RayHit::new(
+				p, normal, material, front_face, id, uv, tangent,
+			));
+		}
 	}
 }
@@ -1,6 +1,98 @@
-use crate::{Ray, Scalar, Vec3, radians, random};
+use crate::{
+	Aperture, BVH, IndependentSampler, Ray, Sampler, Scalar, Vec3, radians, white_balance_gains,
+};
+use std::ops::Range;
 
-#[derive(Clone, PartialEq)]
+// Maps a position on the film (in pixels) to a camera ray, the renderer accepts any of them.
+// Pixel (x, y) covers x..x + 1 and y..y + 1, the film picks the positions within a pixel.
+// Debug is hashed by checkpoints to recognize the camera
+pub trait Projection: Send + Sync + std::fmt::Debug {
+	// None for positions that don't see anything (e.g. outside of a fisheye circle)
+	fn get_ray(&self, x: Scalar, y: Scalar, sampler: &mut dyn Sampler) -> Option<Ray>;
+
+	// exposure and white balance of the film, multiplied with the radiance of every sample
+	fn film_scale(&self) -> Vec3 {
+		Vec3::one()
+	}
+
+	// rows of the film that show the same image as pixel row y, for projections that put several
+	// images on one film (e.g. both eyes of a stereo pair). The filter of a pixel doesn't reach
+	// past them.
+	fn image_rows(&self, _y: usize) -> Range<Scalar> {
+		Scalar::NEG_INFINITY..Scalar::INFINITY
+	}
+}
+
+// Camera settings in photographic units, see Camera::physical
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalCamera {
+	// in millimeters
+	pub focal_length: Scalar,
+	// width of the sensor in millimeters, the height follows from the image aspect ratio
+	pub sensor_width: Scalar,
+	pub f_number: Scalar,
+	// in seconds, also the duration of the motion blur
+	pub shutter_time: Scalar,
+	pub iso: Scalar,
+	// in meters
+	pub focus_dist: Scalar,
+	// color temperature in kelvin that appears white, None keeps the colors as they are
+	pub white_balance: Option<Scalar>,
+}
+
+impl PhysicalCamera {
+	// an emitted radiance of 1 in the scene is this many cd/m²
+	pub const SCENE_LUMINANCE_SCALE: Scalar = 1000.0;
+
+	// exposure value at iso 100
+	pub fn ev100(&self) -> Scalar {
+		(self.f_number * self.f_number / self.shutter_time * 100.0 / self.iso).log2()
+	}
+
+	// scale from scene radiance to film, 1 / the luminance that saturates the film
+	// (saturation based sensitivity, see "Moving Frostbite to PBR")
+	pub fn exposure(&self) -> Scalar {
+		Self::SCENE_LUMINANCE_SCALE / (1.2 * self.ev100().exp2())
+	}
+}
+
+impl Default for PhysicalCamera {
+	// a 50mm lens on full frame at f/2.8, 1/125s and iso 100
+	fn default() -> Self {
+		Self {
+			focal_length: 50.0,
+			sensor_width: 36.0,
+			f_number: 2.8,
+			shutter_time: 1.0 / 125.0,
+			iso: 100.0,
+			focus_dist: Camera::DEFAULT_FOCUS_DIST,
+			white_balance: None,
+		}
+	}
+}
+
+// right (u), up (v) and backwards (w) vectors of a camera looking in direction
+pub(crate) fn camera_basis(direction: Vec3) -> (Vec3, Vec3, Vec3) {
+	let w = -direction.normalize();
+	let mut right = Camera::WORLD_UP.cross(w);
+	// looking straight up or down, the top of the image points to -z when looking down and to +z
+	// when looking up
+	if right.length_squared() < 1e-12 {
+		right = Vec3::new(0.0, 0.0, -w.y.signum()).cross(w);
+	}
+	let u = right.normalize();
+	let v = w.cross(u);
+	(u, v, w)
+}
+
+// rotates v around the normalized axis by angle radians (rodrigues' rotation formula)
+fn rotate_around(v: Vec3, axis: Vec3, angle: Scalar) -> Vec3 {
+	let (sin, cos) = angle.sin_cos();
+	v * cos + axis.cross(v) * sin + axis * (axis.dot(v) * (1.0 - cos))
+}
+
+// Thin lens perspective camera
+#[derive(Debug, Clone, PartialEq)]
 pub struct Camera {
 	pub origin: Vec3,
 	pixel00_loc: Vec3,
@@ -9,6 +101,14 @@ pub struct Camera {
 	defocus_disk_x: Vec3,
 	defocus_disk_y: Vec3,
 	defocus_angle: Scalar,
+	focus_dist: Scalar,
+	aperture: Aperture,
+	// rays are spread over this many seconds, 0 disables motion blur
+	shutter_time: Scalar,
+	film_scale: Vec3,
+	// in pixels, used for the cat's eye vignetting
+	image_center: (Scalar, Scalar),
+	image_radius: Scalar,
 }
 
 impl Camera {
@@ -37,9 +137,7 @@ impl Camera {
 		let viewport_height = 2.0 * h * focus_dist;
 		let viewport_width = viewport_height * (width as Scalar / height as Scalar);
 
-		let w = -direction.normalize();
-		let u = Self::WORLD_UP.cross(w).normalize();
-		let v = w.cross(u);
+		let (u, v, w) = camera_basis(direction);
 
 		// Calculate the vectors across the horizontal and down the vertical viewport edges.
 		let viewport_u = u * viewport_width;
@@ -65,30 +163,214 @@ impl Camera {
 			defocus_disk_x,
 			defocus_disk_y,
 			defocus_angle,
+			focus_dist,
+			aperture: Aperture::CIRCULAR,
+			shutter_time: 0.0,
+			film_scale: Vec3::one(),
+			image_center: (width as Scalar / 2.0, height as Scalar / 2.0),
+			image_radius: ((width * width + height * height) as Scalar).sqrt() / 2.0,
+		}
+	}
+
+	// looks from origin at target, which is also where the camera focuses, up works for any
+	// direction (including straight up and down) and doesn't have to be perpendicular to it
+	pub fn look_at(
+		origin: Vec3,
+		target: Vec3,
+		up: Vec3,
+		fov: Scalar,
+		defocus_angle: Scalar,
+		width: usize,
+		height: usize,
+	) -> Self {
+		let direction = target - origin;
+		Self::new(
+			origin,
+			direction,
+			fov,
+			direction.length().max(1e-6),
+			defocus_angle,
+			width,
+			height,
+		)
+		.with_up(up)
+	}
+
+	// field of view and depth of field from the lens and sensor, exposure from f-number, shutter
+	// time and iso, so that lighting can be set up in photographic units
+	pub fn physical(
+		origin: Vec3,
+		direction: Vec3,
+		settings: &PhysicalCamera,
+		width: usize,
+		height: usize,
+	) -> Self {
+		let focal_length = settings.focal_length / 1000.0;
+		let focus_dist = settings.focus_dist.max(focal_length * 1.001);
+		// thin lens equation, the sensor is further away from the lens than the focal length when
+		// focusing closer than infinity
+		let image_dist = 1.0 / (1.0 / focal_length - 1.0 / focus_dist);
+		let sensor_height = settings.sensor_width / 1000.0 * height as Scalar / width as Scalar;
+		let fov = (2.0 * (sensor_height / (2.0 * image_dist)).atan()).to_degrees();
+		let aperture_radius = focal_length / settings.f_number / 2.0;
+		let defocus_angle = (2.0 * (aperture_radius / focus_dist).atan()).to_degrees();
+
+		let mut film_scale = Vec3::uniform(settings.exposure());
+		if let Some(temperature) = settings.white_balance {
+			film_scale = film_scale * white_balance_gains(temperature);
+		}
+		Self {
+			shutter_time: settings.shutter_time.max(0.0),
+			film_scale,
+			..Self::new(
+				origin,
+				direction,
+				fov,
+				focus_dist,
+				defocus_angle,
+				width,
+				height,
+			)
+		}
+	}
+
+	// rays get a random time between 0 and shutter_time (in seconds) for motion blur, doesn't
+	// change the exposure
+	pub fn with_shutter_time(self, shutter_time: Scalar) -> Self {
+		Self {
+			shutter_time: shutter_time.max(0.0),
+			..self
+		}
+	}
+
+	// shape of the bokeh, the defocus angle stays the size of the aperture
+	pub fn with_aperture(self, aperture: Aperture) -> Self {
+		Self { aperture, ..self }
+	}
+
+	// rotates the camera around its viewing direction, positive angles (in degrees) tilt it
+	// clockwise so the image turns counter-clockwise
+	pub fn with_roll(self, roll: Scalar) -> Self {
+		self.rotated(radians(roll))
+	}
+
+	// turns the camera around its viewing direction until the top of the image points towards up
+	// (e.g. for cameras built with new, which always use WORLD_UP)
+	pub fn with_up(self, up: Vec3) -> Self {
+		let forward = (self.image_center_position() - self.origin).normalize();
+		let target_up = up - forward * up.dot(forward);
+		if target_up.near_zero() {
+			return self;
+		}
+		let target_up = target_up.normalize();
+		let current_up = -self.pixel_delta_y.normalize();
+		let angle = Scalar::atan2(
+			forward.dot(current_up.cross(target_up)),
+			current_up.dot(target_up),
+		);
+		self.rotated(angle)
+	}
+
+	// rotation around the viewing direction by angle radians (clockwise looking along it)
+	fn rotated(self, angle: Scalar) -> Self {
+		let axis = (self.image_center_position() - self.origin).normalize();
+		let rotate = |v: Vec3| rotate_around(v, axis, angle);
+		Self {
+			pixel00_loc: self.origin + rotate(self.pixel00_loc - self.origin),
+			pixel_delta_x: rotate(self.pixel_delta_x),
+			pixel_delta_y: rotate(self.pixel_delta_y),
+			defocus_disk_x: rotate(self.defocus_disk_x),
+			defocus_disk_y: rotate(self.defocus_disk_y),
+			..self
+		}
+	}
+
+	pub fn focus_dist(&self) -> Scalar {
+		self.focus_dist
+	}
+
+	// moves the plane in focus, the field of view and the defocus angle stay the same
+	pub fn with_focus_dist(self, focus_dist: Scalar) -> Self {
+		let scale = focus_dist.max(1e-6) / self.focus_dist;
+		Self {
+			pixel00_loc: self.origin + (self.pixel00_loc - self.origin) * scale,
+			pixel_delta_x: self.pixel_delta_x * scale,
+			pixel_delta_y: self.pixel_delta_y * scale,
+			defocus_disk_x: self.defocus_disk_x * scale,
+			defocus_disk_y: self.defocus_disk_y * scale,
+			focus_dist: focus_dist.max(1e-6),
+			..self
+		}
+	}
+
+	// distance to the plane through whatever is visible at position (x, y) on the film, None if
+	// it is the sky
+	pub fn measure_focus_dist(&self, bvh: &BVH, x: Scalar, y: Scalar) -> Option<Scalar> {
+		let target = self.film_point(x, y);
+		let ray = Ray::new(self.origin, (target - self.origin).normalize());
+		let hit = bvh.trace(&ray, &mut IndependentSampler::new())?;
+		let forward = (self.image_center_position() - self.origin).normalize();
+		Some((hit.point - self.origin).dot(forward))
+	}
+
+	// focuses on whatever is visible at position (x, y) on the film, keeps the focus distance
+	// when it is the sky
+	pub fn with_auto_focus(self, bvh: &BVH, x: Scalar, y: Scalar) -> Self {
+		match self.measure_focus_dist(bvh, x, y) {
+			Some(focus_dist) => self.with_focus_dist(focus_dist),
+			None => self,
 		}
 	}
 
+	// point on the plane in focus in the middle of the image
+	fn image_center_position(&self) -> Vec3 {
+		self.film_point(self.image_center.0, self.image_center.1)
+	}
+
+	// point on the plane in focus seen at position (x, y) on the film
 	#[inline(always)]
-	fn pixel_sample_square(&self, rand: &mut rand::prelude::ThreadRng) -> Vec3 {
-		(random(-0.5, 0.5, rand) * self.pixel_delta_x)
-			+ (random(-0.5, 0.5, rand) * self.pixel_delta_y)
+	fn film_point(&self, x: Scalar, y: Scalar) -> Vec3 {
+		self.pixel00_loc + self.pixel_delta_x * (x - 0.5) + self.pixel_delta_y * (y - 0.5)
 	}
 
+	// None if the lens barrel blocks the sample (cat's eye vignetting)
 	#[inline(always)]
-	fn defocus_disk_sample(&self, rand: &mut rand::prelude::ThreadRng) -> Vec3 {
-		let p = Vec3::random_in_unit_disk(rand);
-		self.origin + (p.x * self.defocus_disk_x) * (p.y * self.defocus_disk_y)
+	fn defocus_disk_sample(&self, x: Scalar, y: Scalar, sampler: &mut dyn Sampler) -> Option<Vec3> {
+		let image_position = (
+			(x - self.image_center.0) / self.image_radius,
+			(self.image_center.1 - y) / self.image_radius,
+		);
+		let (p_x, p_y) = self.aperture.sample(sampler.get_2d(), image_position)?;
+		Some(self.origin + (p_x * self.defocus_disk_x) + (p_y * self.defocus_disk_y))
 	}
 
-	pub fn get_ray(&self, x: Scalar, y: Scalar, rand: &mut rand::prelude::ThreadRng) -> Ray {
-		let pixel_center = self.pixel00_loc + (self.pixel_delta_x * x) + (self.pixel_delta_y * y);
-		let pixel_sample = pixel_center + self.pixel_sample_square(rand);
+	// same image plane seen from a moved eye, gives an off-axis projection for stereo rigs
+	pub(crate) fn with_eye_offset(&self, offset: Vec3) -> Self {
+		Self {
+			origin: self.origin + offset,
+			..self.clone()
+		}
+	}
+}
+
+impl Projection for Camera {
+	fn get_ray(&self, x: Scalar, y: Scalar, sampler: &mut dyn Sampler) -> Option<Ray> {
+		let pixel_sample = self.film_point(x, y);
 		let ray_origin: Vec3 = if self.defocus_angle <= 0.0 {
 			self.origin
 		} else {
-			self.defocus_disk_sample(rand)
+			self.defocus_disk_sample(x, y, sampler)?
 		};
-		Ray::new(ray_origin, (pixel_sample - ray_origin).normalize())
+		let ray = Ray::new(ray_origin, (pixel_sample - ray_origin).normalize());
+		if self.shutter_time > 0.0 {
+			Some(ray.with_time(sampler.get_1d() * self.shutter_time))
+		} else {
+			Some(ray)
+		}
+	}
+
+	fn film_scale(&self) -> Vec3 {
+		self.film_scale
 	}
 }
 
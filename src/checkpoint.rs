@@ -0,0 +1,278 @@
+use crate::{AovPixel, FilmPixel, Scalar, Vec3};
+use std::{
+	fmt,
+	fs::File,
+	io::{self, BufReader, BufWriter, Read, Write},
+};
+
+// Everything a RenderSession accumulated so far, saved to disk so that an interrupted render can
+// be continued or a finished one can get more samples. The hashes make sure the samples are only
+// combined with samples of the same scene, camera and settings.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+	pub width: usize,
+	pub height: usize,
+	pub pass: u32,
+	pub scene_hash: u64,
+	pub camera_hash: u64,
+	pub settings_hash: u64,
+	pub pixels: Vec<FilmPixel>,
+	// None if the session didn't collect aovs
+	pub aovs: Option<Vec<AovPixel>>,
+}
+
+impl Checkpoint {
+	const MAGIC: &[u8; 4] = b"RTCP";
+	const VERSION: u32 = 1;
+
+	// writes to a temporary file first, an interrupted save never destroys the last checkpoint
+	pub fn save(&self, path: &str) -> Result<(), String> {
+		let temporary_path = format!("{path}.tmp");
+		File::create(&temporary_path)
+			.and_then(|file| {
+				let mut writer = BufWriter::new(file);
+				self.write_to(&mut writer)?;
+				writer.flush()
+			})
+			.map_err(|error| format!("failed to write {temporary_path}: {error}"))?;
+		std::fs::rename(&temporary_path, path)
+			.map_err(|error| format!("failed to rename {temporary_path} to {path}: {error}"))
+	}
+
+	pub fn open(path: &str) -> Result<Self, String> {
+		let file = File::open(path).map_err(|error| format!("failed to open {path}: {error}"))?;
+		Self::read_from(&mut BufReader::new(file))
+			.map_err(|error| format!("failed to read {path}: {error}"))
+	}
+
+	fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+		writer.write_all(Self::MAGIC)?;
+		write_u32(writer, Self::VERSION)?;
+		write_u64(writer, self.width as u64)?;
+		write_u64(writer, self.height as u64)?;
+		write_u32(writer, self.pass)?;
+		write_u64(writer, self.scene_hash)?;
+		write_u64(writer, self.camera_hash)?;
+		write_u64(writer, self.settings_hash)?;
+		for pixel in &self.pixels {
+			pixel.write_to(writer)?;
+		}
+		write_u8(writer, self.aovs.is_some() as u8)?;
+		for pixel in self.aovs.iter().flatten() {
+			pixel.write_to(writer)?;
+		}
+		Ok(())
+	}
+
+	fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+		let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+		let mut magic = [0; 4];
+		reader.read_exact(&mut magic)?;
+		if &magic != Self::MAGIC {
+			return Err(invalid("not a checkpoint"));
+		}
+		if read_u32(reader)? != Self::VERSION {
+			return Err(invalid("unsupported checkpoint version"));
+		}
+		let width = read_u64(reader)? as usize;
+		let height = read_u64(reader)? as usize;
+		let pass = read_u32(reader)?;
+		let scene_hash = read_u64(reader)?;
+		let camera_hash = read_u64(reader)?;
+		let settings_hash = read_u64(reader)?;
+		let pixels = (0..width * height)
+			.map(|_| FilmPixel::read_from(reader))
+			.collect::<io::Result<_>>()?;
+		let aovs = if read_u8(reader)? != 0 {
+			Some(
+				(0..width * height)
+					.map(|_| AovPixel::read_from(reader))
+					.collect::<io::Result<_>>()?,
+			)
+		} else {
+			None
+		};
+		Ok(Self {
+			width,
+			height,
+			pass,
+			scene_hash,
+			camera_hash,
+			settings_hash,
+			pixels,
+			aovs,
+		})
+	}
+}
+
+// FNV-1a hash of the debug output, it doesn't change between runs (unlike the std hasher, which
+// is also not implemented for floats)
+pub(crate) fn debug_hash(value: &impl fmt::Debug) -> u64 {
+	struct Hasher(u64);
+
+	impl fmt::Write for Hasher {
+		fn write_str(&mut self, s: &str) -> fmt::Result {
+			for byte in s.bytes() {
+				self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100000001b3);
+			}
+			Ok(())
+		}
+	}
+
+	let mut hasher = Hasher(0xcbf29ce484222325);
+	fmt::write(&mut hasher, format_args!("{value:?}")).unwrap();
+	hasher.0
+}
+
+// little endian helpers for the pixel types
+
+pub(crate) fn write_u8(writer: &mut impl Write, value: u8) -> io::Result<()> {
+	writer.write_all(&[value])
+}
+
+pub(crate) fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+	writer.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn write_u64(writer: &mut impl Write, value: u64) -> io::Result<()> {
+	writer.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn write_scalar(writer: &mut impl Write, value: Scalar) -> io::Result<()> {
+	writer.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn write_vec3(writer: &mut impl Write, value: Vec3) -> io::Result<()> {
+	write_scalar(writer, value.x)?;
+	write_scalar(writer, value.y)?;
+	write_scalar(writer, value.z)
+}
+
+pub(crate) fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+	let mut bytes = [0; 1];
+	reader.read_exact(&mut bytes)?;
+	Ok(bytes[0])
+}
+
+pub(crate) fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+	let mut bytes = [0; 4];
+	reader.read_exact(&mut bytes)?;
+	Ok(u32::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+	let mut bytes = [0; 8];
+	reader.read_exact(&mut bytes)?;
+	Ok(u64::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_scalar(reader: &mut impl Read) -> io::Result<Scalar> {
+	let mut bytes = [0; 8];
+	reader.read_exact(&mut bytes)?;
+	Ok(Scalar::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_vec3(reader: &mut impl Read) -> io::Result<Vec3> {
+	Ok(Vec3::new(
+		read_scalar(reader)?,
+		read_scalar(reader)?,
+		read_scalar(reader)?,
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Aovs, SurfaceAovs};
+
+	fn test_checkpoint(aovs: bool) -> Checkpoint {
+		let (width, height) = (3, 2);
+		let pixels = (0..width * height)
+			.map(|i| {
+				let mut pixel = FilmPixel::new();
+				for sample in 0..=i {
+					let color = Vec3::new(i as Scalar * 0.1, sample as Scalar * 0.3, 1.0 / 3.0);
+					pixel.add_sample(color, 0.5 + sample as Scalar, None);
+				}
+				pixel
+			})
+			.collect();
+		let aovs = aovs.then(|| {
+			(0..width * height)
+				.map(|i| {
+					let mut pixel = AovPixel::new();
+					let mut aovs = Aovs::new();
+					aovs.albedo = Vec3::uniform(0.25);
+					aovs.add_light(1, Vec3::new(1.0, 2.0, 3.0));
+					aovs.surface = (i % 2 == 0).then(|| SurfaceAovs {
+						depth: 2.5,
+						position: Vec3::new(1.0, -1.0, i as Scalar),
+						normal: Vec3::new(0.0, 1.0, 0.0),
+						object_id: i,
+						material_id: 42,
+					});
+					pixel.add_sample(&aovs);
+					pixel
+				})
+				.collect()
+		});
+		Checkpoint {
+			width,
+			height,
+			pass: 7,
+			scene_hash: 1,
+			camera_hash: u64::MAX,
+			settings_hash: 0x0123456789abcdef,
+			pixels,
+			aovs,
+		}
+	}
+
+	fn assert_same(a: &Checkpoint, b: &Checkpoint) {
+		// the pixels have no PartialEq, their debug output shows every float exactly
+		assert_eq!(format!("{a:?}"), format!("{b:?}"));
+	}
+
+	#[test]
+	fn save_and_open_round_trip() {
+		let directory = std::env::temp_dir();
+		for aovs in [false, true] {
+			let checkpoint = test_checkpoint(aovs);
+			let path = directory.join(format!(
+				"raytracer_checkpoint_test_{}_{aovs}.checkpoint",
+				std::process::id()
+			));
+			let path = path.to_str().unwrap();
+			checkpoint.save(path).unwrap();
+			let opened = Checkpoint::open(path);
+			std::fs::remove_file(path).unwrap();
+			assert_same(&checkpoint, &opened.unwrap());
+		}
+	}
+
+	#[test]
+	fn read_rejects_other_files() {
+		let mut bytes = Vec::new();
+		test_checkpoint(true).write_to(&mut bytes).unwrap();
+		assert_same(
+			&test_checkpoint(true),
+			&Checkpoint::read_from(&mut bytes.as_slice()).unwrap(),
+		);
+
+		let mut wrong_magic = bytes.clone();
+		wrong_magic[0] = b'X';
+		assert!(Checkpoint::read_from(&mut wrong_magic.as_slice()).is_err());
+		let mut wrong_version = bytes.clone();
+		wrong_version[4] += 1;
+		assert!(Checkpoint::read_from(&mut wrong_version.as_slice()).is_err());
+		let truncated = &bytes[..bytes.len() - 1];
+		assert!(Checkpoint::read_from(&mut &truncated[..]).is_err());
+	}
+
+	#[test]
+	fn debug_hash_is_stable() {
+		// FNV-1a test vectors
+		assert_eq!(debug_hash(&format_args!("")), 0xcbf29ce484222325);
+		assert_eq!(debug_hash(&format_args!("a")), 0xaf63dc4c8601ec8c);
+	}
+}
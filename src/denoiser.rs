@@ -0,0 +1,99 @@
+use crate::{AovBuffer, Scalar, Vec3};
+use rayon::prelude::*;
+
+// Edge-avoiding à-trous wavelet filter (Dammertz et al. 2010),
+// edges are detected with the color, albedo and normal of the neighbouring pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DenoiseSettings {
+	// every iteration doubles the filter radius, starting at 2 pixels
+	pub iterations: u32,
+	// the smaller the sigmas, the more the corresponding feature preserves edges
+	pub color_sigma: Scalar,
+	pub albedo_sigma: Scalar,
+	pub normal_sigma: Scalar,
+}
+
+impl DenoiseSettings {
+	pub const DEFAULT_ITERATIONS: u32 = 5;
+	pub const DEFAULT_COLOR_SIGMA: Scalar = 4.0;
+	pub const DEFAULT_ALBEDO_SIGMA: Scalar = 0.1;
+	pub const DEFAULT_NORMAL_SIGMA: Scalar = 0.3;
+}
+
+impl Default for DenoiseSettings {
+	fn default() -> Self {
+		Self {
+			iterations: Self::DEFAULT_ITERATIONS,
+			color_sigma: Self::DEFAULT_COLOR_SIGMA,
+			albedo_sigma: Self::DEFAULT_ALBEDO_SIGMA,
+			normal_sigma: Self::DEFAULT_NORMAL_SIGMA,
+		}
+	}
+}
+
+// B3 spline
+const KERNEL: [Scalar; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+// color and features must have the same size, returns the filtered color
+pub fn denoise(color: &[Vec3], features: &AovBuffer, settings: &DenoiseSettings) -> Vec<Vec3> {
+	let width = features.width;
+	let height = features.height;
+	assert_eq!(color.len(), width * height);
+
+	let albedo: Vec<Vec3> = features.pixels.iter().map(|pixel| pixel.albedo()).collect();
+	let normal: Vec<Vec3> = features.pixels.iter().map(|pixel| pixel.normal()).collect();
+
+	let mut input = color.to_vec();
+	let mut output = vec![Vec3::zero(); color.len()];
+	for iteration in 0..settings.iterations {
+		let step = 1 << iteration;
+		// finer levels are already smoothed, so color edges get stricter
+		let color_sigma = settings.color_sigma / (1 << iteration) as Scalar;
+
+		output
+			.par_chunks_exact_mut(width)
+			.enumerate()
+			.for_each(|(y, row)| {
+				for (x, output_color) in row.iter_mut().enumerate() {
+					let center = y * width + x;
+					let mut color_sum = Vec3::zero();
+					let mut weight_sum = 0.0;
+					for (ky, ky_weight) in KERNEL.iter().enumerate() {
+						let sample_y = y as isize + (ky as isize - 2) * step;
+						if sample_y < 0 || sample_y >= height as isize {
+							continue;
+						}
+						for (kx, kx_weight) in KERNEL.iter().enumerate() {
+							let sample_x = x as isize + (kx as isize - 2) * step;
+							if sample_x < 0 || sample_x >= width as isize {
+								continue;
+							}
+							let sample = sample_y as usize * width + sample_x as usize;
+
+							let color_weight =
+								edge_weight(input[center], input[sample], color_sigma);
+							let albedo_weight =
+								edge_weight(albedo[center], albedo[sample], settings.albedo_sigma);
+							let normal_weight =
+								edge_weight(normal[center], normal[sample], settings.normal_sigma);
+							let weight = ky_weight
+								* kx_weight * color_weight
+								* albedo_weight * normal_weight;
+							color_sum = color_sum + input[sample] * weight;
+							weight_sum += weight;
+						}
+					}
+					// the center pixel always has a weight > 0
+					*output_color = color_sum / weight_sum;
+				}
+			});
+
+		std::mem::swap(&mut input, &mut output);
+	}
+	input
+}
+
+#[inline(always)]
+fn edge_weight(a: Vec3, b: Vec3, sigma: Scalar) -> Scalar {
+	(-(a - b).length_squared() / (sigma * sigma)).exp()
+}
@@ -0,0 +1,423 @@
+use crate::{
+	PixelVariance, Scalar, Vec3,
+	checkpoint::{read_scalar, read_u32, read_vec3, write_scalar, write_u32, write_vec3},
+	math::PI,
+};
+use std::io::{self, Read, Write};
+
+// Samples whose luminance is more than `sigma` standard deviations above the pixel's running mean
+// are clamped down to that bound, once the statistics are stable after `min_samples`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlierRejection {
+	pub min_samples: u32,
+	pub sigma: Scalar,
+}
+
+impl OutlierRejection {
+	pub const DEFAULT_MIN_SAMPLES: u32 = 8;
+	pub const DEFAULT_SIGMA: Scalar = 3.0;
+}
+
+impl Default for OutlierRejection {
+	fn default() -> Self {
+		Self {
+			min_samples: Self::DEFAULT_MIN_SAMPLES,
+			sigma: Self::DEFAULT_SIGMA,
+		}
+	}
+}
+
+// Reconstruction filter, weights a sample by its offset (in pixels) from the pixel center.
+// All of them are separable and 0 outside of radius.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+	// every sample inside of the pixel counts the same, sharp but aliases
+	Box {
+		radius: Scalar,
+	},
+	Tent {
+		radius: Scalar,
+	},
+	// sigma is the standard deviation, the curve is shifted down to reach 0 at radius
+	Gaussian {
+		radius: Scalar,
+		sigma: Scalar,
+	},
+	// cubic with negative lobes that sharpen edges, b = c = 1/3 is the recommended balance between
+	// blurring and ringing
+	Mitchell {
+		radius: Scalar,
+		b: Scalar,
+		c: Scalar,
+	},
+	// windowed filter similar to a gaussian with less blur
+	BlackmanHarris {
+		radius: Scalar,
+	},
+}
+
+impl Filter {
+	pub const NAMES: [&str; 5] = ["box", "tent", "gaussian", "mitchell", "blackman-harris"];
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"box" => Some(Self::Box { radius: 0.5 }),
+			"tent" => Some(Self::Tent { radius: 1.0 }),
+			"gaussian" => Some(Self::Gaussian {
+				radius: 1.5,
+				sigma: 0.5,
+			}),
+			"mitchell" => Some(Self::Mitchell {
+				radius: 2.0,
+				b: 1.0 / 3.0,
+				c: 1.0 / 3.0,
+			}),
+			"blackman-harris" => Some(Self::BlackmanHarris { radius: 1.5 }),
+			_ => None,
+		}
+	}
+
+	pub fn radius(&self) -> Scalar {
+		match self {
+			Self::Box { radius }
+			| Self::Tent { radius }
+			| Self::Gaussian { radius, .. }
+			| Self::Mitchell { radius, .. }
+			| Self::BlackmanHarris { radius } => *radius,
+		}
+	}
+
+	#[inline(always)]
+	pub fn evaluate(&self, x: Scalar, y: Scalar) -> Scalar {
+		self.evaluate_1d(x) * self.evaluate_1d(y)
+	}
+
+	fn evaluate_1d(&self, x: Scalar) -> Scalar {
+		let x = x.abs();
+		match *self {
+			Self::Box { radius } => {
+				if x <= radius {
+					1.0
+				} else {
+					0.0
+				}
+			}
+			Self::Tent { radius } => (1.0 - x / radius).max(0.0),
+			Self::Gaussian { radius, sigma } => {
+				let gaussian = |x: Scalar| (-x * x / (2.0 * sigma * sigma)).exp();
+				(gaussian(x) - gaussian(radius)).max(0.0)
+			}
+			Self::Mitchell { radius, b, c } => {
+				// the cubic is defined on [-2, 2]
+				let x = 2.0 * x / radius;
+				if x < 1.0 {
+					((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+						+ (-18.0 + 12.0 * b + 6.0 * c) * x * x
+						+ (6.0 - 2.0 * b)) / 6.0
+				} else if x < 2.0 {
+					((-b - 6.0 * c) * x * x * x
+						+ (6.0 * b + 30.0 * c) * x * x
+						+ (-12.0 * b - 48.0 * c) * x
+						+ (8.0 * b + 24.0 * c))
+						/ 6.0
+				} else {
+					0.0
+				}
+			}
+			Self::BlackmanHarris { radius } => {
+				if x >= radius {
+					return 0.0;
+				}
+				// the window goes from -radius to radius
+				let t = 2.0 * PI * (x + radius) / (2.0 * radius);
+				0.35875 - 0.48829 * t.cos() + 0.14128 * (2.0 * t).cos() - 0.01168 * (3.0 * t).cos()
+			}
+		}
+	}
+}
+
+impl Default for Filter {
+	fn default() -> Self {
+		Self::Box { radius: 0.5 }
+	}
+}
+
+// Importance samples the offsets of a filter, proportional to its absolute value so that wide
+// filters don't waste samples where they are close to 0. Renderers take the samples of a pixel
+// with it around the pixel center and add them to that pixel.
+#[derive(Debug, Clone)]
+pub struct FilterSampler {
+	filter: Filter,
+	// piecewise constant distribution of the absolute 1d filter over -radius..radius, both axes
+	// are sampled from it since the filters are separable
+	cdf: Vec<Scalar>,
+}
+
+impl FilterSampler {
+	const RESOLUTION: usize = 64;
+
+	pub fn new(filter: Filter) -> Self {
+		let radius = filter.radius();
+		let bin_width = 2.0 * radius / Self::RESOLUTION as Scalar;
+		let mut cdf = Vec::with_capacity(Self::RESOLUTION + 1);
+		cdf.push(0.0);
+		for i in 0..Self::RESOLUTION {
+			let x = -radius + (i as Scalar + 0.5) * bin_width;
+			cdf.push(cdf[i] + filter.evaluate_1d(x).abs() * bin_width);
+		}
+		let integral = cdf[Self::RESOLUTION];
+		for value in &mut cdf {
+			*value /= integral;
+		}
+		Self { filter, cdf }
+	}
+
+	// offset from the pixel center together with the sample weight (filter value / pdf), the
+	// weights of box filters are all the same while negative lobes give negative weights
+	#[inline(always)]
+	pub fn sample(&self, u: (Scalar, Scalar)) -> (Scalar, Scalar, Scalar) {
+		let (x, pdf_x) = self.sample_1d(u.0);
+		let (y, pdf_y) = self.sample_1d(u.1);
+		(x, y, self.filter.evaluate(x, y) / (pdf_x * pdf_y))
+	}
+
+	#[inline(always)]
+	fn sample_1d(&self, u: Scalar) -> (Scalar, Scalar) {
+		let radius = self.filter.radius();
+		let bin_width = 2.0 * radius / Self::RESOLUTION as Scalar;
+		let bin = (self.cdf.partition_point(|&value| value <= u) - 1).min(Self::RESOLUTION - 1);
+		let bin_probability = self.cdf[bin + 1] - self.cdf[bin];
+		let t = if bin_probability > 0.0 {
+			(u - self.cdf[bin]) / bin_probability
+		} else {
+			0.5
+		};
+		let x = -radius + (bin as Scalar + t) * bin_width;
+		(x, bin_probability / bin_width)
+	}
+}
+
+// Weighted sum of all samples of one pixel together with their luminance statistics, samples are
+// summed in linear color space so that noisy (e.g. spectral) samples average to the right color
+#[derive(Debug, Clone, Copy)]
+pub struct FilmPixel {
+	weighted_sum: Vec3,
+	weight_sum: Scalar,
+	variance: PixelVariance,
+	rejected_count: u32,
+}
+
+impl FilmPixel {
+	pub fn new() -> Self {
+		Self {
+			weighted_sum: Vec3::zero(),
+			weight_sum: 0.0,
+			variance: PixelVariance::new(),
+			rejected_count: 0,
+		}
+	}
+
+	// color is in linear color space, the statistics are gathered in gamma space and ignore the
+	// weight
+	#[inline(always)]
+	pub fn add_sample(
+		&mut self,
+		mut color: Vec3,
+		weight: Scalar,
+		outlier_rejection: Option<&OutlierRejection>,
+	) {
+		let mut gamma_color = color.linear_to_gamma();
+		if let Some(rejection) = outlier_rejection
+			&& self.variance.sample_count() >= rejection.min_samples
+		{
+			let max_luminance =
+				self.variance.mean() + rejection.sigma * self.variance.variance().sqrt();
+			let luminance = gamma_color.luminance();
+			if luminance > max_luminance {
+				let scale = max_luminance / luminance;
+				gamma_color = gamma_color * scale;
+				color = color * (scale * scale);
+				self.rejected_count += 1;
+			}
+		}
+		self.weighted_sum = self.weighted_sum + color * weight;
+		self.weight_sum += weight;
+		self.variance.add_sample(gamma_color);
+	}
+
+	// weighted average in linear color space, negative filter lobes can't make it negative
+	pub fn color(&self) -> Vec3 {
+		if self.weight_sum <= 0.0 {
+			Vec3::zero()
+		} else {
+			(self.weighted_sum / self.weight_sum).clamp(0.0, Scalar::INFINITY)
+		}
+	}
+
+	pub fn sample_count(&self) -> u32 {
+		self.variance.sample_count()
+	}
+
+	// how many samples were clamped as outliers
+	pub fn rejected_count(&self) -> u32 {
+		self.rejected_count
+	}
+
+	pub fn variance(&self) -> &PixelVariance {
+		&self.variance
+	}
+
+	pub(crate) fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+		write_vec3(writer, self.weighted_sum)?;
+		write_scalar(writer, self.weight_sum)?;
+		self.variance.write_to(writer)?;
+		write_u32(writer, self.rejected_count)
+	}
+
+	pub(crate) fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+		Ok(Self {
+			weighted_sum: read_vec3(reader)?,
+			weight_sum: read_scalar(reader)?,
+			variance: PixelVariance::read_from(reader)?,
+			rejected_count: read_u32(reader)?,
+		})
+	}
+}
+
+impl Default for FilmPixel {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+// Image the samples are reconstructed on. Positions on the film are in pixels, pixel (x, y)
+// covers x..x + 1 and y..y + 1, so its center is at (x + 0.5, y + 0.5).
+// Renderers take the samples of a pixel with a FilterSampler around its center and add them to
+// that pixel.
+#[derive(Debug, Clone)]
+pub struct Film {
+	pub width: usize,
+	pub height: usize,
+	pub pixels: Vec<FilmPixel>,
+	pub filter: Filter,
+	// None keeps every sample as is
+	pub outlier_rejection: Option<OutlierRejection>,
+}
+
+impl Film {
+	pub fn new(
+		width: usize,
+		height: usize,
+		filter: Filter,
+		outlier_rejection: Option<OutlierRejection>,
+	) -> Self {
+		Self {
+			width,
+			height,
+			pixels: vec![FilmPixel::new(); width * height],
+			filter,
+			outlier_rejection,
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.pixels.fill(FilmPixel::new());
+	}
+
+	// color of every pixel in linear color space
+	pub fn resolve(&self) -> Vec<Vec3> {
+		self.pixels.iter().map(|pixel| pixel.color()).collect()
+	}
+
+	// gamma corrected 8 bit image
+	pub fn resolve_to_image(&self) -> image::RgbImage {
+		image::RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+			let color = self.pixels[y as usize * self.width + x as usize]
+				.color()
+				.linear_to_gamma();
+			image::Rgb([
+				(color.x * 255.0) as u8,
+				(color.y * 255.0) as u8,
+				(color.z * 255.0) as u8,
+			])
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn filters() -> Vec<Filter> {
+		Filter::NAMES
+			.iter()
+			.map(|name| Filter::from_name(name).unwrap())
+			.collect()
+	}
+
+	// stratified over the unit square
+	fn grid(n: usize) -> impl Iterator<Item = (Scalar, Scalar)> {
+		(0..n * n).map(move |i| {
+			(
+				((i % n) as Scalar + 0.5) / n as Scalar,
+				((i / n) as Scalar + 0.5) / n as Scalar,
+			)
+		})
+	}
+
+	#[test]
+	fn sample_weights_integrate_the_filter() {
+		for filter in filters() {
+			let radius = filter.radius();
+			let steps = 4096;
+			let step = 2.0 * radius / steps as Scalar;
+			let integral_1d: Scalar = (0..steps)
+				.map(|i| filter.evaluate_1d(-radius + (i as Scalar + 0.5) * step) * step)
+				.sum();
+
+			let sampler = FilterSampler::new(filter);
+			let n = 256;
+			let mean_weight = grid(n)
+				.map(|u| {
+					let (x, y, weight) = sampler.sample(u);
+					assert!(x.abs() <= radius && y.abs() <= radius, "{filter:?} {x} {y}");
+					weight
+				})
+				.sum::<Scalar>()
+				/ (n * n) as Scalar;
+			let integral = integral_1d * integral_1d;
+			assert!(
+				(mean_weight - integral).abs() < 0.01 * integral,
+				"{filter:?}: {mean_weight} != {integral}"
+			);
+		}
+	}
+
+	#[test]
+	fn box_weights_are_constant() {
+		let sampler = FilterSampler::new(Filter::default());
+		for u in grid(16) {
+			let (_, _, weight) = sampler.sample(u);
+			assert!((weight - 1.0).abs() < 1e-9, "{weight}");
+		}
+	}
+
+	#[test]
+	fn pixels_normalize_by_the_weight_sum() {
+		let color = Vec3::new(0.2, 0.5, 0.9);
+		for filter in filters() {
+			let sampler = FilterSampler::new(filter);
+			let mut pixel = FilmPixel::new();
+			for u in grid(8) {
+				let (_, _, weight) = sampler.sample(u);
+				pixel.add_sample(color, weight, None);
+			}
+			assert!(
+				(pixel.color() - color).length() < 1e-9,
+				"{filter:?}: {:?}",
+				pixel.color()
+			);
+			assert_eq!(pixel.sample_count(), 64);
+		}
+	}
+}
@@ -0,0 +1,502 @@
+use crate::{Projection, Ray, Sampler, Scalar, Vec3, camera::camera_basis, math::PI};
+use std::path::Path;
+
+// lens descriptions are in millimeters, scenes in meters
+const MILLIMETER: Scalar = 0.001;
+
+// rays from the film that get blocked inside of the lens are retried this many times
+const MAX_LENS_SAMPLES: u32 = 8;
+
+// Shape of the aperture, gives the shape of the bokeh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aperture {
+	// less than 3 blades is a circular aperture
+	pub blades: u32,
+	// in degrees
+	pub rotation: Scalar,
+	// 0..1, how much the lens barrel cuts off the aperture towards the image corners
+	pub cat_eye: Scalar,
+}
+
+impl Aperture {
+	pub const CIRCULAR: Self = Self {
+		blades: 0,
+		rotation: 0.0,
+		cat_eye: 0.0,
+	};
+
+	pub fn polygonal(blades: u32, rotation: Scalar) -> Self {
+		Self {
+			blades,
+			rotation,
+			cat_eye: 0.0,
+		}
+	}
+
+	pub fn with_cat_eye(self, cat_eye: Scalar) -> Self {
+		Self { cat_eye, ..self }
+	}
+
+	// uniform point on the aperture (radius 1), image_position goes from -1 to 1 from the image
+	// center to the corners, None if the point is cut off by the cat's eye vignetting
+	#[inline(always)]
+	pub fn sample(
+		&self,
+		sample: (Scalar, Scalar),
+		image_position: (Scalar, Scalar),
+	) -> Option<(Scalar, Scalar)> {
+		let (x, y) = if self.blades < 3 {
+			let p = Vec3::sample_in_unit_disk(sample);
+			(p.x, p.y)
+		} else {
+			// pick a blade, then a uniform point in the triangle between the center and its edge
+			let blades = self.blades as Scalar;
+			let blade = (sample.0 * blades).floor().min(blades - 1.0);
+			let s = (sample.0 * blades - blade).sqrt();
+			let angle_a = self.blade_angle(blade);
+			let angle_b = self.blade_angle(blade + 1.0);
+			(
+				s * ((1.0 - sample.1) * angle_a.cos() + sample.1 * angle_b.cos()),
+				s * ((1.0 - sample.1) * angle_a.sin() + sample.1 * angle_b.sin()),
+			)
+		};
+
+		// the front of the lens barrel is a second unit circle that moves outwards towards the
+		// image corners, only the overlap lets light through
+		let offset_x = x - image_position.0 * self.cat_eye;
+		let offset_y = y - image_position.1 * self.cat_eye;
+		if self.cat_eye > 0.0 && offset_x * offset_x + offset_y * offset_y > 1.0 {
+			return None;
+		}
+		Some((x, y))
+	}
+
+	// whether a point (relative to the radius) lies inside of the aperture
+	#[inline(always)]
+	pub fn contains(&self, x: Scalar, y: Scalar) -> bool {
+		if self.blades < 3 {
+			return x * x + y * y <= 1.0;
+		}
+		let blades = self.blades as Scalar;
+		let sector_angle = 2.0 * PI / blades;
+		let angle = (y.atan2(x) - self.blade_angle(0.0)).rem_euclid(2.0 * PI);
+		let blade = (angle / sector_angle).floor();
+		// distance along the direction to the middle of the blade edge
+		let middle = self.blade_angle(blade + 0.5);
+		x * middle.cos() + y * middle.sin() <= (sector_angle / 2.0).cos()
+	}
+
+	#[inline(always)]
+	fn blade_angle(&self, blade: Scalar) -> Scalar {
+		self.rotation.to_radians() + blade * 2.0 * PI / self.blades as Scalar
+	}
+}
+
+impl Default for Aperture {
+	fn default() -> Self {
+		Self::CIRCULAR
+	}
+}
+
+// One surface of a lens system, listed from the front (scene side) to the back (film side)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LensElement {
+	// positive if the center of curvature is on the film side, 0 for the aperture stop
+	pub curvature_radius: Scalar,
+	// distance to the next surface (or the film for the last one)
+	pub thickness: Scalar,
+	// of the medium behind the surface, 0 or 1 for air
+	pub ior: Scalar,
+	pub aperture_radius: Scalar,
+}
+
+impl LensElement {
+	fn is_stop(&self) -> bool {
+		self.curvature_radius == 0.0
+	}
+
+	fn medium_ior(&self) -> Scalar {
+		if self.ior == 0.0 { 1.0 } else { self.ior }
+	}
+}
+
+// Multi-element lens described by its surfaces
+#[derive(Debug, Clone, PartialEq)]
+pub struct LensSystem {
+	pub elements: Vec<LensElement>,
+}
+
+impl LensSystem {
+	// one surface per line: curvature radius, thickness, ior and aperture diameter in millimeters,
+	// # starts a comment (the format of the lens files of pbrt)
+	pub fn parse(description: &str) -> Result<Self, String> {
+		let mut elements = Vec::new();
+		for (line_index, line) in description.lines().enumerate() {
+			let line = line.split('#').next().unwrap_or_default().trim();
+			if line.is_empty() {
+				continue;
+			}
+			let values = line
+				.split_whitespace()
+				.map(|value| value.parse::<Scalar>())
+				.collect::<Result<Vec<_>, _>>()
+				.map_err(|error| format!("line {}: {error}", line_index + 1))?;
+			let [curvature_radius, thickness, ior, aperture_diameter] = values[..] else {
+				return Err(format!(
+					"line {}: expected 4 values, got {}",
+					line_index + 1,
+					values.len()
+				));
+			};
+			elements.push(LensElement {
+				curvature_radius: curvature_radius * MILLIMETER,
+				thickness: thickness * MILLIMETER,
+				ior,
+				aperture_radius: aperture_diameter / 2.0 * MILLIMETER,
+			});
+		}
+		if elements.is_empty() {
+			return Err("lens description has no elements".to_string());
+		}
+		Ok(Self { elements })
+	}
+
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+		let description = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+		Self::parse(&description)
+	}
+
+	// f/2 double gauss, US patent 2,673,491 (Tronnier), scaled to a focal length of 50mm
+	pub fn double_gauss_50mm() -> Self {
+		Self::parse(
+			"29.475 3.76 1.67 25.2
+			84.83 0.12 1 25.2
+			19.275 4.025 1.67 23
+			40.77 3.275 1.699 23
+			12.75 5.705 1 18
+			0 4.5 0 17.1
+			-14.495 1.18 1.603 17
+			40.77 6.065 1.658 20
+			-20.385 0.19 1 20
+			437.065 3.22 1.717 20
+			-39.73 5 1 20",
+		)
+		.unwrap()
+	}
+
+	// stops down the aperture stop to a diameter in millimeters, it can't open wider than designed
+	pub fn with_aperture_diameter(mut self, diameter: Scalar) -> Self {
+		for element in self.elements.iter_mut().filter(|element| element.is_stop()) {
+			element.aperture_radius = element.aperture_radius.min(diameter / 2.0 * MILLIMETER);
+		}
+		self
+	}
+}
+
+// Camera that traces rays through every surface of a real lens, depth of field, vignetting,
+// distortion and cat's eye bokeh all come from the lens itself.
+// Camera space: the film is at z = 0, the lens in front of it at positive z.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RealisticCamera {
+	pub origin: Vec3,
+	u: Vec3,
+	v: Vec3,
+	w: Vec3,
+	lens: LensSystem,
+	// z of the vertex of every element
+	element_z: Vec<Scalar>,
+	aperture: Aperture,
+	pixel_size: Scalar,
+	width: usize,
+	height: usize,
+}
+
+impl RealisticCamera {
+	// full frame
+	pub const DEFAULT_SENSOR_WIDTH: Scalar = 36.0;
+
+	// sensor_width is in millimeters, focus_dist is measured from the film
+	pub fn new(
+		origin: Vec3,
+		direction: Vec3,
+		lens: LensSystem,
+		sensor_width: Scalar,
+		focus_dist: Scalar,
+		width: usize,
+		height: usize,
+	) -> Self {
+		let (u, v, w) = camera_basis(direction);
+		let film_distance = focus(&lens, focus_dist);
+		Self {
+			origin,
+			u,
+			v,
+			w,
+			element_z: element_positions(&lens, film_distance),
+			lens,
+			aperture: Aperture::CIRCULAR,
+			pixel_size: sensor_width * MILLIMETER / width as Scalar,
+			width,
+			height,
+		}
+	}
+
+	// shape of the aperture stop, the cat's eye of the aperture is ignored as the lens has its own
+	pub fn with_aperture(self, aperture: Aperture) -> Self {
+		Self { aperture, ..self }
+	}
+}
+
+impl Projection for RealisticCamera {
+	fn get_ray(&self, x: Scalar, y: Scalar, sampler: &mut dyn Sampler) -> Option<Ray> {
+		// the lens flips the image
+		let film_point = Vec3::new(
+			-(x - self.width as Scalar / 2.0) * self.pixel_size,
+			(y - self.height as Scalar / 2.0) * self.pixel_size,
+			0.0,
+		);
+
+		let rear = self.lens.elements.last()?;
+		let rear_z = *self.element_z.last()?;
+		for _ in 0..MAX_LENS_SAMPLES {
+			let p = Vec3::sample_in_unit_disk(sampler.get_2d()) * rear.aperture_radius;
+			let lens_point = Vec3::new(p.x, p.y, rear_z);
+			let ray = Ray::new(film_point, (lens_point - film_point).normalize());
+			if let Some(ray) = trace_lens(&self.lens, &self.element_z, &self.aperture, ray) {
+				return Some(Ray::new(
+					self.origin + self.u * ray.origin.x + self.v * ray.origin.y
+						- self.w * ray.origin.z,
+					(self.u * ray.dir.x + self.v * ray.dir.y - self.w * ray.dir.z).normalize(),
+				));
+			}
+		}
+		None
+	}
+}
+
+// vertex positions of the elements with the last one film_distance in front of the film
+fn element_positions(lens: &LensSystem, film_distance: Scalar) -> Vec<Scalar> {
+	let mut element_z = vec![0.0; lens.elements.len()];
+	let mut z = film_distance;
+	for i in (0..lens.elements.len()).rev() {
+		element_z[i] = z;
+		if i > 0 {
+			z += lens.elements[i - 1].thickness;
+		}
+	}
+	element_z
+}
+
+// traces a camera space ray from the film through the lens, None if it gets blocked
+fn trace_lens(
+	lens: &LensSystem,
+	element_z: &[Scalar],
+	aperture: &Aperture,
+	mut ray: Ray,
+) -> Option<Ray> {
+	for (i, element) in lens.elements.iter().enumerate().rev() {
+		let (t, normal) = if element.is_stop() {
+			if ray.dir.z <= 0.0 {
+				return None;
+			}
+			((element_z[i] - ray.origin.z) / ray.dir.z, Vec3::zero())
+		} else {
+			intersect_surface(element_z[i], element.curvature_radius, &ray)?
+		};
+		let p = ray.at(t);
+		let (x, y) = (p.x / element.aperture_radius, p.y / element.aperture_radius);
+		if element.is_stop() {
+			if !aperture.contains(x, y) {
+				return None;
+			}
+			ray.origin = p;
+			continue;
+		}
+		if x * x + y * y > 1.0 {
+			return None;
+		}
+
+		// going towards the scene: from the medium behind the surface into the one in front of it
+		let ior_in_front = if i > 0 {
+			lens.elements[i - 1].medium_ior()
+		} else {
+			1.0
+		};
+		let etai_over_etat = element.medium_ior() / ior_in_front;
+		let dir = ray.dir.normalize();
+		let cos_theta = (-dir).dot(normal).min(1.0);
+		if etai_over_etat * (1.0 - cos_theta * cos_theta).sqrt() > 1.0 {
+			return None;
+		}
+		ray = Ray::new(p, Vec3::refract(dir, normal, etai_over_etat));
+	}
+	Some(ray)
+}
+
+// distance along the ray and the normal (facing the ray) of a spherical lens surface
+#[inline(always)]
+fn intersect_surface(vertex_z: Scalar, radius: Scalar, ray: &Ray) -> Option<(Scalar, Vec3)> {
+	let center = Vec3::new(0.0, 0.0, vertex_z - radius);
+	let oc = ray.origin - center;
+	let a = ray.dir.length_squared();
+	let half_b = oc.dot(ray.dir);
+	let c = oc.length_squared() - radius * radius;
+	let discriminant = half_b * half_b - a * c;
+	if discriminant < 0.0 {
+		return None;
+	}
+	let sqrt_discriminant = discriminant.sqrt();
+	let (t0, t1) = (
+		(-half_b - sqrt_discriminant) / a,
+		(-half_b + sqrt_discriminant) / a,
+	);
+	// the vertex is on the far side of the sphere for rays that travel towards the center
+	let use_closer = (ray.dir.z > 0.0) == (radius < 0.0);
+	let t = if use_closer { t0 } else { t1 };
+	if t <= 0.0 {
+		return None;
+	}
+	let mut normal = (ray.at(t) - center).normalize();
+	if normal.dot(ray.dir) > 0.0 {
+		normal = -normal;
+	}
+	Some((t, normal))
+}
+
+// film distance (of the last element) that puts objects at focus_dist in focus: a paraxial ray
+// from the film center has to cross the axis again at focus_dist
+fn focus(lens: &LensSystem, focus_dist: Scalar) -> Scalar {
+	let no_aperture = Aperture::CIRCULAR;
+	let rear = lens.elements.last().copied().unwrap_or(LensElement {
+		curvature_radius: 0.0,
+		thickness: 0.0,
+		ior: 0.0,
+		aperture_radius: 0.0,
+	});
+	let focused_distance = |film_distance: Scalar| {
+		let element_z = element_positions(lens, film_distance);
+		let target = Vec3::new(rear.aperture_radius * 0.05, 0.0, film_distance);
+		let ray = Ray::new(Vec3::zero(), target.normalize());
+		match trace_lens(lens, &element_z, &no_aperture, ray) {
+			// converges on the axis in front of the lens
+			Some(ray) if ray.dir.x * ray.origin.x < 0.0 => {
+				ray.origin.z - ray.origin.x / ray.dir.x * ray.dir.z
+			}
+			_ => Scalar::INFINITY,
+		}
+	};
+
+	// further away from the lens focuses closer
+	let mut near = 0.0;
+	let mut far = rear.thickness.max(MILLIMETER);
+	for _ in 0..64 {
+		if focused_distance(far) <= focus_dist {
+			break;
+		}
+		near = far;
+		far *= 1.5;
+	}
+	for _ in 0..64 {
+		let middle = (near + far) / 2.0;
+		if focused_distance(middle) > focus_dist {
+			near = middle;
+		} else {
+			far = middle;
+		}
+	}
+	(near + far) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn circular_aperture_contains_the_unit_disk() {
+		let aperture = Aperture::CIRCULAR;
+		assert!(aperture.contains(0.0, 0.0));
+		assert!(aperture.contains(0.7, 0.7));
+		assert!(aperture.contains(-1.0, 0.0));
+		assert!(!aperture.contains(0.72, 0.72));
+		assert!(!aperture.contains(0.0, -1.01));
+	}
+
+	#[test]
+	fn polygonal_aperture_contains_its_polygon() {
+		// a square with its corners on the axes
+		let aperture = Aperture::polygonal(4, 0.0);
+		assert!(aperture.contains(0.99, 0.0));
+		assert!(aperture.contains(0.0, -0.99));
+		assert!(aperture.contains(0.49, 0.49));
+		assert!(!aperture.contains(0.51, 0.51));
+		assert!(!aperture.contains(-0.6, -0.6));
+
+		// rotated by 45° the edges are on the axes
+		let aperture = Aperture::polygonal(4, 45.0);
+		let half_edge = (0.5 as Scalar).sqrt();
+		assert!(aperture.contains(half_edge - 1e-6, 0.0));
+		assert!(!aperture.contains(half_edge + 1e-6, 0.0));
+		assert!(aperture.contains(half_edge - 1e-6, half_edge - 1e-6));
+	}
+
+	#[test]
+	fn aperture_samples_lie_inside_of_it() {
+		for aperture in [Aperture::CIRCULAR, Aperture::polygonal(6, 15.0)] {
+			for i in 0..16 {
+				for j in 0..16 {
+					let sample = ((i as Scalar + 0.5) / 16.0, (j as Scalar + 0.5) / 16.0);
+					let (x, y) = aperture.sample(sample, (0.0, 0.0)).unwrap();
+					assert!(
+						aperture.contains(x * 0.999, y * 0.999),
+						"{aperture:?} {x} {y}"
+					);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn parse_converts_to_meters_and_skips_comments() {
+		let lens = LensSystem::parse(
+			"# radius thickness ior diameter
+			50 5 1.5 20
+
+			0 2 0 10 # stop
+			-50 30 1 20",
+		)
+		.unwrap();
+		assert_eq!(lens.elements.len(), 3);
+		assert_eq!(
+			lens.elements[0],
+			LensElement {
+				curvature_radius: 0.05,
+				thickness: 0.005,
+				ior: 1.5,
+				aperture_radius: 0.01,
+			}
+		);
+		assert!(lens.elements[1].is_stop());
+		assert_eq!(lens.elements[1].aperture_radius, 0.005);
+		assert_eq!(lens.elements[2].curvature_radius, -0.05);
+	}
+
+	#[test]
+	fn parse_rejects_malformed_descriptions() {
+		assert!(LensSystem::parse("").is_err());
+		assert!(LensSystem::parse("# only a comment").is_err());
+		let error = LensSystem::parse("50 5 1.5 20\n50 5 1.5").unwrap_err();
+		assert!(error.starts_with("line 2"), "{error}");
+		let error = LensSystem::parse("50 5 glass 20").unwrap_err();
+		assert!(error.starts_with("line 1"), "{error}");
+	}
+
+	#[test]
+	fn double_gauss_has_one_stop() {
+		let lens = LensSystem::double_gauss_50mm();
+		assert_eq!(lens.elements.len(), 11);
+		assert_eq!(lens.elements.iter().filter(|e| e.is_stop()).count(), 1);
+		let stopped_down = lens.with_aperture_diameter(5.0);
+		let stop = stopped_down.elements.iter().find(|e| e.is_stop()).unwrap();
+		assert_eq!(stop.aperture_radius, 0.0025);
+	}
+}
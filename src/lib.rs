@@ -1,7 +1,7 @@
 mod math;
 pub use math::{AABB, Ray, Scalar, Vec3, radians, random};
 mod scene;
-pub use scene::{Bounded, Hittable, Object, RayHit, Renderable, Scene};
+pub use scene::{Bounded, Crossing, Hit, Hittable, Object, RayHit, Renderable, Scene, Solid, Span};
 mod scenes;
 pub use scenes::{
 	combine_spheres_and_cubes, create_10_metallic_scene, create_glass_scene, create_sample_scene,
@@ -10,10 +10,50 @@ pub use scenes::{
 mod bvh;
 pub use bvh::BVH;
 mod camera;
-pub use camera::{Camera, get_camera_rotation};
+pub use camera::{Camera, PhysicalCamera, Projection, get_camera_rotation};
+mod projections;
+pub use projections::{
+	EquirectangularCamera, FisheyeCamera, OrthographicCamera, ProjectionKind, ProjectionSettings,
+};
+mod stereo;
+pub use stereo::{OdsCamera, StereoCamera};
+mod lens;
+pub use lens::{Aperture, LensElement, LensSystem, RealisticCamera};
+mod animation;
+pub use animation::{
+	Animatable, AnimatedScene, CameraKeyframe, CameraTrack, Interpolation, Keyframe,
+	ObjectAnimation, Track,
+};
+mod texture;
+pub use texture::{ImageTexture, ScalarTexture};
 mod materials;
 pub use materials::{Material, Scattered};
 mod shapes;
-pub use shapes::{Cube, Sphere};
+pub use shapes::{Csg, CsgOperation, Cube, Moving, Sphere};
 mod renderer;
-pub use renderer::render;
+mod subsurface;
+pub use renderer::{RenderSettings, render, render_with_aovs};
+mod aovs;
+pub use aovs::{AovBuffer, AovLayer, AovPixel, Aovs, SurfaceAovs};
+mod denoiser;
+pub use denoiser::{DenoiseSettings, denoise};
+mod adaptive;
+pub use adaptive::{AdaptiveSampling, PixelVariance, sample_heatmap_color};
+mod film;
+pub use film::{Film, FilmPixel, Filter, FilterSampler, OutlierRejection};
+mod tiles;
+pub use tiles::{Tile, TileOrder};
+mod checkpoint;
+pub use checkpoint::Checkpoint;
+mod session;
+pub use session::{CancelToken, RenderProgress, RenderSession};
+mod spectral;
+pub use spectral::{
+	Ior, SPECTRAL_SAMPLES, SampledSpectrum, SampledWavelengths, WAVELENGTH_D_LINE, WAVELENGTH_MAX,
+	WAVELENGTH_MIN, white_balance_gains,
+};
+mod sampler;
+pub use sampler::{
+	BlueNoiseSampler, HaltonSampler, IndependentSampler, Sampler, SamplerKind, SobolSampler,
+	StratifiedSampler,
+};
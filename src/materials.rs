@@ -1,10 +1,83 @@
-use crate::{Ray, RayHit, Scalar, Vec3, random};
+use crate::{
+	ImageTexture, Ior, Ray, RayHit, Sampler, Scalar, ScalarTexture, Vec3, WAVELENGTH_D_LINE,
+	math::PI,
+	spectral::{reflectance_to_rgb, rgb_to_spectrum},
+};
+use std::{
+	borrow::Cow,
+	hash::{DefaultHasher, Hash, Hasher},
+	ops::{Add, Mul},
+	sync::Arc,
+};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum Material {
-	Lambertain { albedo: Vec3, emission: Scalar },
-	Metal { albedo: Vec3, fuzz: Scalar },
-	Dielectric { ir: Scalar },
+	Lambertain {
+		albedo: Vec3,
+		emission: Scalar,
+	},
+	Metal {
+		albedo: Vec3,
+		fuzz: Scalar,
+	},
+	Dielectric {
+		ir: Scalar,
+	},
+	// glass whose ior depends on the wavelength, only disperses light in the spectral renderer
+	DispersiveDielectric {
+		ior: Ior,
+	},
+	// interference layer on top of a metal or dielectric base (soap bubbles, oil slicks, coated
+	// lenses), thickness is in nanometers, other bases ignore the film
+	ThinFilm {
+		base: Box<Material>,
+		thickness: Scalar,
+		ior: Scalar,
+	},
+	// glossy dielectric layer over any base material
+	Clearcoat {
+		base: Box<Material>,
+		ior: Scalar,
+		roughness: Scalar,
+	},
+	// translucent material (skin, wax, marble, milk) rendered with a random walk through the inside,
+	// only works on closed objects
+	Subsurface {
+		albedo: Vec3,
+		mean_free_path: Vec3,
+		ir: Scalar,
+	},
+	// tangent space normal map (opengl convention, green points up), strength scales the tilt
+	NormalMap {
+		base: Box<Material>,
+		map: Arc<ImageTexture>,
+		strength: Scalar,
+	},
+	// the luminance of the texture is the height, strength is the height of a white texel
+	// relative to the size of a texel
+	BumpMap {
+		base: Box<Material>,
+		height: Arc<ImageTexture>,
+		strength: Scalar,
+	},
+	// holes in the surface (leaves, fences, decals), rays pass through transparent parts,
+	// 0 is fully transparent, 1 opaque, values in between let that fraction of rays through
+	Cutout {
+		base: Box<Material>,
+		opacity: ScalarTexture,
+	},
+	// every hit picks one of the two materials at random, weight is the probability of second
+	Mix {
+		first: Box<Material>,
+		second: Box<Material>,
+		weight: ScalarTexture,
+	},
+	// any material as a layer over a base, the opacity of the coat is its coverage (e.g. patches of
+	// rust over metal or dust over glass), light the coat lets through scatters off the base
+	Layered {
+		coat: Box<Material>,
+		base: Box<Material>,
+	},
 }
 
 pub struct Scattered {
@@ -12,17 +85,226 @@ pub struct Scattered {
 	pub scattered: Ray,
 }
 
+// wavelength bands the thin film interference is integrated over
+const THIN_FILM_BANDS: usize = 16;
+
 impl Material {
+	pub fn with_thin_film(self, thickness: Scalar, ior: Scalar) -> Self {
+		Self::ThinFilm {
+			base: Box::new(self),
+			thickness,
+			ior,
+		}
+	}
+
+	pub fn with_clearcoat(self, ior: Scalar, roughness: Scalar) -> Self {
+		Self::Clearcoat {
+			base: Box::new(self),
+			ior,
+			roughness,
+		}
+	}
+
+	pub fn with_normal_map(self, map: Arc<ImageTexture>, strength: Scalar) -> Self {
+		Self::NormalMap {
+			base: Box::new(self),
+			map,
+			strength,
+		}
+	}
+
+	pub fn with_bump_map(self, height: Arc<ImageTexture>, strength: Scalar) -> Self {
+		Self::BumpMap {
+			base: Box::new(self),
+			height,
+			strength,
+		}
+	}
+
+	pub fn with_opacity(self, opacity: ScalarTexture) -> Self {
+		Self::Cutout {
+			base: Box::new(self),
+			opacity,
+		}
+	}
+
+	pub fn mix(first: Material, second: Material, weight: ScalarTexture) -> Self {
+		Self::Mix {
+			first: Box::new(first),
+			second: Box::new(second),
+			weight,
+		}
+	}
+
+	pub fn with_coating(self, coat: Material) -> Self {
+		Self::Layered {
+			coat: Box::new(coat),
+			base: Box::new(self),
+		}
+	}
+
+	// resolves every mix of the material at the hit once, also below modifiers and coatings, so that
+	// emission, shading normal and scattering all come from the same material. Every mix on the way
+	// takes one random number from the sampler.
+	#[inline(always)]
+	pub fn choose(&self, uv: (Scalar, Scalar), sampler: &mut dyn Sampler) -> Cow<'_, Material> {
+		match self {
+			Self::Mix {
+				first,
+				second,
+				weight,
+			} => {
+				if sampler.get_1d() < mix_weight(weight, uv) {
+					second.choose(uv, sampler)
+				} else {
+					first.choose(uv, sampler)
+				}
+			}
+			Self::ThinFilm {
+				base,
+				thickness,
+				ior,
+			} if base.has_mix() => Cow::Owned(Self::ThinFilm {
+				base: Box::new(base.choose(uv, sampler).into_owned()),
+				thickness: *thickness,
+				ior: *ior,
+			}),
+			Self::Clearcoat {
+				base,
+				ior,
+				roughness,
+			} if base.has_mix() => Cow::Owned(Self::Clearcoat {
+				base: Box::new(base.choose(uv, sampler).into_owned()),
+				ior: *ior,
+				roughness: *roughness,
+			}),
+			Self::NormalMap {
+				base,
+				map,
+				strength,
+			} if base.has_mix() => Cow::Owned(Self::NormalMap {
+				base: Box::new(base.choose(uv, sampler).into_owned()),
+				map: map.clone(),
+				strength: *strength,
+			}),
+			Self::BumpMap {
+				base,
+				height,
+				strength,
+			} if base.has_mix() => Cow::Owned(Self::BumpMap {
+				base: Box::new(base.choose(uv, sampler).into_owned()),
+				height: height.clone(),
+				strength: *strength,
+			}),
+			Self::Cutout { base, opacity } if base.has_mix() => Cow::Owned(Self::Cutout {
+				base: Box::new(base.choose(uv, sampler).into_owned()),
+				opacity: opacity.clone(),
+			}),
+			Self::Layered { coat, base } if self.has_mix() => Cow::Owned(Self::Layered {
+				coat: Box::new(coat.choose(uv, sampler).into_owned()),
+				base: Box::new(base.choose(uv, sampler).into_owned()),
+			}),
+			_ => Cow::Borrowed(self),
+		}
+	}
+
+	// whether choose has anything to resolve
+	#[inline(always)]
+	pub fn has_mix(&self) -> bool {
+		match self {
+			Self::Mix { .. } => true,
+			Self::ThinFilm { base, .. }
+			| Self::Clearcoat { base, .. }
+			| Self::NormalMap { base, .. }
+			| Self::BumpMap { base, .. }
+			| Self::Cutout { base, .. } => base.has_mix(),
+			Self::Layered { coat, base } => coat.has_mix() || base.has_mix(),
+			_ => false,
+		}
+	}
+
+	// opacity at the texture coordinates of a hit, BVH::trace skips hits that turn out transparent
+	#[inline(always)]
+	pub fn opacity(&self, uv: (Scalar, Scalar)) -> Scalar {
+		match self {
+			Self::Cutout { opacity, .. } => opacity.at(uv),
+			Self::ThinFilm { base, .. }
+			| Self::Clearcoat { base, .. }
+			| Self::NormalMap { base, .. }
+			| Self::BumpMap { base, .. }
+			| Self::Layered { base, .. } => base.opacity(uv),
+			Self::Mix {
+				first,
+				second,
+				weight,
+			} => lerp(
+				first.opacity(uv),
+				second.opacity(uv),
+				mix_weight(weight, uv),
+			),
+			_ => 1.0,
+		}
+	}
+
+	// normal used for shading the hit, the renderer replaces RayHit::normal with it before scatter
+	pub fn shading_normal(&self, hit: &RayHit) -> Vec3 {
+		match self {
+			// both maps perturb the shading normal of their base, so they can be stacked
+			Self::NormalMap {
+				base,
+				map,
+				strength,
+			} => {
+				let normal = base.shading_normal(hit);
+				let (tangent, bitangent) = tangent_frame(normal, hit.tangent);
+				let tangent_normal = map.sample(hit.uv) * 2.0 - Vec3::one();
+				(tangent * (tangent_normal.x * strength)
+					+ bitangent * (tangent_normal.y * strength)
+					+ normal * tangent_normal.z)
+					.normalize()
+			}
+			Self::BumpMap {
+				base,
+				height,
+				strength,
+			} => {
+				let normal = base.shading_normal(hit);
+				let (tangent, bitangent) = tangent_frame(normal, hit.tangent);
+				let (du, dv) = height.texel_size();
+				let (u, v) = hit.uv;
+				let h = height.sample((u, v)).luminance();
+				// v grows downwards in the image, the bitangent points upwards
+				let dh_du = height.sample((u + du, v)).luminance() - h;
+				let dh_dv = h - height.sample((u, v + dv)).luminance();
+				(normal - (tangent * dh_du + bitangent * dh_dv) * *strength).normalize()
+			}
+			Self::ThinFilm { base, .. }
+			| Self::Clearcoat { base, .. }
+			| Self::Cutout { base, .. }
+			| Self::Layered { base, .. } => base.shading_normal(hit),
+			Self::Mix {
+				first,
+				second,
+				weight,
+			} => {
+				let weight = mix_weight(weight, hit.uv);
+				(first.shading_normal(hit) * (1.0 - weight) + second.shading_normal(hit) * weight)
+					.normalize()
+			}
+			_ => hit.normal,
+		}
+	}
+
 	#[inline(always)]
 	pub fn scatter(
 		&self,
 		ray_in: &Ray,
 		hit: &RayHit,
-		rand: &mut rand::prelude::ThreadRng,
+		sampler: &mut dyn Sampler,
 	) -> Option<Scattered> {
 		match self {
 			Self::Lambertain { albedo, .. } => {
-				let mut scatter_direction = hit.normal + Vec3::random_unit_vector(rand);
+				let mut scatter_direction = hit.normal + Vec3::sample_unit_vector(sampler.get_2d());
 				if scatter_direction.near_zero() {
 					scatter_direction = hit.normal;
 				}
@@ -32,47 +314,484 @@ impl Material {
 				})
 			}
 			Self::Metal { albedo, fuzz } => {
-				let reflected = ray_in.dir.normalize().reflect(hit.normal);
-				let scattered_dir = reflected + *fuzz * Vec3::random_unit_vector(rand);
-				if scattered_dir.dot(hit.normal) <= 0.0 {
-					return None;
+				Self::scatter_metal(ray_in, hit, *albedo, *fuzz, sampler)
+			}
+			Self::Dielectric { ir } => Self::scatter_dielectric(ray_in, hit, *ir, None, sampler),
+			Self::DispersiveDielectric { ior } => {
+				Self::scatter_dielectric(ray_in, hit, ior.at(WAVELENGTH_D_LINE), None, sampler)
+			}
+			Self::ThinFilm {
+				base,
+				thickness,
+				ior,
+			} => match base.as_ref() {
+				Self::Metal { albedo, fuzz } => {
+					let cos_theta = (-ray_in.dir.normalize()).dot(hit.normal).clamp(0.0, 1.0);
+					// the metal is approximated by the real ior that matches its albedo as reflectance
+					let attenuation = reflectance_to_rgb(THIN_FILM_BANDS, |wavelength| {
+						let f0 = rgb_to_spectrum(*albedo, wavelength).clamp(0.0, 0.99).sqrt();
+						let metal_ior = (1.0 + f0) / (1.0 - f0);
+						thin_film_reflectance(
+							cos_theta, 1.0, *ior, metal_ior, *thickness, wavelength,
+						)
+					});
+					Self::scatter_metal(ray_in, hit, attenuation.clamp(0.0, 1.0), *fuzz, sampler)
+				}
+				Self::Dielectric { ir } => {
+					Self::scatter_dielectric(ray_in, hit, *ir, Some((*thickness, *ior)), sampler)
+				}
+				Self::DispersiveDielectric { ior: base_ior } => Self::scatter_dielectric(
+					ray_in,
+					hit,
+					base_ior.at(WAVELENGTH_D_LINE),
+					Some((*thickness, *ior)),
+					sampler,
+				),
+				_ => base.scatter(ray_in, hit, sampler),
+			},
+			// the normal was already perturbed by the renderer, the opacity is handled by the bvh
+			Self::NormalMap { base, .. }
+			| Self::BumpMap { base, .. }
+			| Self::Cutout { base, .. } => base.scatter(ray_in, hit, sampler),
+			// only the boundary, the inside is handled by the random walk of the renderer
+			Self::Subsurface { ir, .. } => {
+				Self::scatter_dielectric(ray_in, hit, *ir, None, sampler)
+			}
+			Self::Mix {
+				first,
+				second,
+				weight,
+			} => {
+				if sampler.get_1d() < mix_weight(weight, hit.uv) {
+					second.scatter(ray_in, hit, sampler)
+				} else {
+					first.scatter(ray_in, hit, sampler)
+				}
+			}
+			Self::Layered { coat, base } => {
+				if !hit.front_face || sampler.get_1d() >= coat.opacity(hit.uv) {
+					return base.scatter(ray_in, hit, sampler);
+				}
+				let coat_scattered = coat.scatter(ray_in, hit, sampler)?;
+				if coat_scattered.scattered.dir.dot(hit.normal) > 0.0 {
+					return Some(coat_scattered);
+				}
+				// transmitted through the coat, which tints what the base scatters
+				let mut base_scattered = base.scatter(ray_in, hit, sampler)?;
+				base_scattered.attenuation =
+					base_scattered.attenuation * coat_scattered.attenuation;
+				Some(base_scattered)
+			}
+			Self::Clearcoat {
+				base,
+				ior,
+				roughness,
+			} => {
+				if !hit.front_face {
+					return base.scatter(ray_in, hit, sampler);
+				}
+				let cos_theta = (-ray_in.dir.normalize()).dot(hit.normal).clamp(0.0, 1.0);
+				// the coat reflects with the probability of its fresnel term, the rest reaches the base
+				if sampler.get_1d() < Self::reflectance(cos_theta, *ior) {
+					Self::scatter_metal(ray_in, hit, Vec3::one(), *roughness, sampler)
+				} else {
+					base.scatter(ray_in, hit, sampler)
 				}
-				let scattered = Ray::new(hit.point, scattered_dir);
-				Some(Scattered {
-					attenuation: *albedo,
-					scattered,
-				})
 			}
-			Self::Dielectric { ir } => {
-				let refration_ratio = if hit.front_face { 1.0 / *ir } else { *ir };
+		}
+	}
 
-				let unit_dir = ray_in.dir.normalize();
-				let cos_theta = Scalar::min((-unit_dir).dot(hit.normal), 1.0);
-				let sin_theta = Scalar::sqrt(1.0 - (cos_theta * cos_theta));
+	#[inline(always)]
+	fn scatter_metal(
+		ray_in: &Ray,
+		hit: &RayHit,
+		attenuation: Vec3,
+		fuzz: Scalar,
+		sampler: &mut dyn Sampler,
+	) -> Option<Scattered> {
+		let reflected = ray_in.dir.normalize().reflect(hit.normal);
+		let scattered_dir = reflected + fuzz * Vec3::sample_unit_vector(sampler.get_2d());
+		if scattered_dir.dot(hit.normal) <= 0.0 {
+			return None;
+		}
+		let scattered = Ray::new(hit.point, scattered_dir);
+		Some(Scattered {
+			attenuation,
+			scattered,
+		})
+	}
 
-				let cannot_refract = refration_ratio * sin_theta > 1.0;
-				let direction = if cannot_refract
-					|| (Self::reflectance(cos_theta, refration_ratio) > random(0.0, 1.0, rand))
-				{
-					unit_dir.reflect(hit.normal)
-				} else {
-					Vec3::refract(unit_dir, hit.normal, refration_ratio)
-				};
+	// film is (thickness, ior) of an optional thin film on the surface
+	#[inline(always)]
+	fn scatter_dielectric(
+		ray_in: &Ray,
+		hit: &RayHit,
+		ir: Scalar,
+		film: Option<(Scalar, Scalar)>,
+		sampler: &mut dyn Sampler,
+	) -> Option<Scattered> {
+		let refration_ratio = if hit.front_face { 1.0 / ir } else { ir };
 
-				Some(Scattered {
-					attenuation: Vec3::one(),
-					scattered: Ray::new(hit.point, direction),
-				})
+		let unit_dir = ray_in.dir.normalize();
+		let cos_theta = Scalar::min((-unit_dir).dot(hit.normal), 1.0);
+		let sin_theta = Scalar::sqrt(1.0 - (cos_theta * cos_theta));
+
+		let cannot_refract = refration_ratio * sin_theta > 1.0;
+		let reflect = || Ray::new(hit.point, unit_dir.reflect(hit.normal));
+		let refract = || {
+			Ray::new(
+				hit.point,
+				Vec3::refract(unit_dir, hit.normal, refration_ratio),
+			)
+		};
+
+		let Some((thickness, film_ior)) = film else {
+			let scattered = if cannot_refract
+				|| (Self::reflectance(cos_theta, refration_ratio) > sampler.get_1d())
+			{
+				reflect()
+			} else {
+				refract()
+			};
+			return Some(Scattered {
+				attenuation: Vec3::one(),
+				scattered,
+			});
+		};
+		if cannot_refract {
+			return Some(Scattered {
+				attenuation: Vec3::one(),
+				scattered: reflect(),
+			});
+		}
+
+		// the film reflects every wavelength differently, so reflection and refraction are picked
+		// with the average reflectance and weighted by the color
+		let (outside_ior, inside_ior) = if hit.front_face { (1.0, ir) } else { (ir, 1.0) };
+		let reflectance = reflectance_to_rgb(THIN_FILM_BANDS, |wavelength| {
+			thin_film_reflectance(
+				cos_theta,
+				outside_ior,
+				film_ior,
+				inside_ior,
+				thickness,
+				wavelength,
+			)
+		})
+		.clamp(0.0, 1.0);
+		let reflect_probability =
+			((reflectance.x + reflectance.y + reflectance.z) / 3.0).clamp(0.001, 0.999);
+		Some(if sampler.get_1d() < reflect_probability {
+			Scattered {
+				attenuation: reflectance / reflect_probability,
+				scattered: reflect(),
+			}
+		} else {
+			Scattered {
+				attenuation: (Vec3::one() - reflectance) / (1.0 - reflect_probability),
+				scattered: refract(),
 			}
+		})
+	}
+
+	// the material as seen by light of a single wavelength (in nanometers)
+	#[inline(always)]
+	pub fn at_wavelength(&self, wavelength: Scalar) -> Cow<'_, Material> {
+		match self {
+			Self::DispersiveDielectric { ior } => Cow::Owned(Self::Dielectric {
+				ir: ior.at(wavelength),
+			}),
+			Self::ThinFilm {
+				base,
+				thickness,
+				ior,
+			} if base.is_dispersive() => Cow::Owned(Self::ThinFilm {
+				base: Box::new(base.at_wavelength(wavelength).into_owned()),
+				thickness: *thickness,
+				ior: *ior,
+			}),
+			Self::Clearcoat {
+				base,
+				ior,
+				roughness,
+			} if base.is_dispersive() => Cow::Owned(Self::Clearcoat {
+				base: Box::new(base.at_wavelength(wavelength).into_owned()),
+				ior: *ior,
+				roughness: *roughness,
+			}),
+			Self::NormalMap {
+				base,
+				map,
+				strength,
+			} if base.is_dispersive() => Cow::Owned(Self::NormalMap {
+				base: Box::new(base.at_wavelength(wavelength).into_owned()),
+				map: map.clone(),
+				strength: *strength,
+			}),
+			Self::BumpMap {
+				base,
+				height,
+				strength,
+			} if base.is_dispersive() => Cow::Owned(Self::BumpMap {
+				base: Box::new(base.at_wavelength(wavelength).into_owned()),
+				height: height.clone(),
+				strength: *strength,
+			}),
+			Self::Cutout { base, opacity } if base.is_dispersive() => Cow::Owned(Self::Cutout {
+				base: Box::new(base.at_wavelength(wavelength).into_owned()),
+				opacity: opacity.clone(),
+			}),
+			Self::Mix {
+				first,
+				second,
+				weight,
+			} if self.is_dispersive() => Cow::Owned(Self::Mix {
+				first: Box::new(first.at_wavelength(wavelength).into_owned()),
+				second: Box::new(second.at_wavelength(wavelength).into_owned()),
+				weight: weight.clone(),
+			}),
+			Self::Layered { coat, base } if self.is_dispersive() => Cow::Owned(Self::Layered {
+				coat: Box::new(coat.at_wavelength(wavelength).into_owned()),
+				base: Box::new(base.at_wavelength(wavelength).into_owned()),
+			}),
+			_ => Cow::Borrowed(self),
+		}
+	}
+
+	// albedo and mean free path of a subsurface base below modifiers and coatings, paths that
+	// refract into it continue with a random walk. Mixes have to be chosen first.
+	pub fn subsurface_medium(&self) -> Option<(Vec3, Vec3)> {
+		match self {
+			Self::Subsurface {
+				albedo,
+				mean_free_path,
+				..
+			} => Some((*albedo, *mean_free_path)),
+			Self::ThinFilm { base, .. }
+			| Self::Clearcoat { base, .. }
+			| Self::NormalMap { base, .. }
+			| Self::BumpMap { base, .. }
+			| Self::Cutout { base, .. }
+			| Self::Layered { base, .. } => base.subsurface_medium(),
+			_ => None,
+		}
+	}
+
+	// whether the scattered direction depends on the wavelength
+	#[inline(always)]
+	pub fn is_dispersive(&self) -> bool {
+		match self {
+			Self::DispersiveDielectric { ior } => ior.is_dispersive(),
+			Self::ThinFilm { base, .. }
+			| Self::Clearcoat { base, .. }
+			| Self::NormalMap { base, .. }
+			| Self::BumpMap { base, .. }
+			| Self::Cutout { base, .. } => base.is_dispersive(),
+			Self::Mix { first, second, .. } => first.is_dispersive() || second.is_dispersive(),
+			Self::Layered { coat, base } => coat.is_dispersive() || base.is_dispersive(),
+			_ => false,
+		}
+	}
+
+	// blurs the scattered direction of (near) specular materials until they are at least as rough as
+	// min_roughness, trades a bit of bias for far fewer fireflies from caustic paths
+	#[inline(always)]
+	pub fn scatter_regularized(
+		&self,
+		ray_in: &Ray,
+		hit: &RayHit,
+		min_roughness: Scalar,
+		sampler: &mut dyn Sampler,
+	) -> Option<Scattered> {
+		let mut scattered = self.scatter(ray_in, hit, sampler)?;
+		let extra_roughness = min_roughness - self.roughness();
+		if extra_roughness > 0.0 {
+			let dir = scattered.scattered.dir.normalize();
+			let roughened = dir + extra_roughness * Vec3::sample_unit_vector(sampler.get_2d());
+			// the blurred direction must stay on the same side of the surface
+			if roughened.dot(hit.normal).signum() == dir.dot(hit.normal).signum() {
+				scattered.scattered.dir = roughened;
+			}
+		}
+		Some(scattered)
+	}
+
+	// 0 for perfect mirrors, 1 for fully diffuse surfaces
+	#[inline(always)]
+	pub fn roughness(&self) -> Scalar {
+		match self {
+			Material::Dielectric { .. } | Material::DispersiveDielectric { .. } => 0.0,
+			// light leaves subsurface materials in all directions
+			Material::Lambertain { .. } | Material::Subsurface { .. } => 1.0,
+			Material::Metal { fuzz, .. } => fuzz.min(1.0),
+			Material::ThinFilm { base, .. }
+			| Material::Clearcoat { base, .. }
+			| Material::NormalMap { base, .. }
+			| Material::BumpMap { base, .. }
+			| Material::Cutout { base, .. }
+			| Material::Layered { base, .. } => base.roughness(),
+			// mixes are usually resolved with Material::choose before, this is the average
+			Material::Mix {
+				first,
+				second,
+				weight,
+			} => lerp(
+				first.roughness(),
+				second.roughness(),
+				weight.average().clamp(0.0, 1.0),
+			),
 		}
 	}
 
 	#[inline(always)]
 	pub fn emission_color(&self) -> Vec3 {
 		match self {
-			Material::Dielectric { .. } => Vec3::zero(),
+			Material::Dielectric { .. } | Material::DispersiveDielectric { .. } => Vec3::zero(),
 			Material::Lambertain { albedo, emission } => (*albedo) * (*emission),
-			Material::Metal { .. } => Vec3::zero(),
+			Material::Metal { .. } | Material::Subsurface { .. } => Vec3::zero(),
+			Material::ThinFilm { base, .. }
+			| Material::Clearcoat { base, .. }
+			| Material::NormalMap { base, .. }
+			| Material::BumpMap { base, .. }
+			| Material::Cutout { base, .. }
+			| Material::Layered { base, .. } => base.emission_color(),
+			// mixes are usually resolved with Material::choose before, this is the average
+			Material::Mix {
+				first,
+				second,
+				weight,
+			} => lerp(
+				first.emission_color(),
+				second.emission_color(),
+				weight.average().clamp(0.0, 1.0),
+			),
+		}
+	}
+
+	// base color without lighting, used for the albedo aov
+	#[inline(always)]
+	pub fn albedo(&self) -> Vec3 {
+		match self {
+			Material::Dielectric { .. } | Material::DispersiveDielectric { .. } => Vec3::one(),
+			Material::Lambertain { albedo, .. } => *albedo,
+			Material::Metal { albedo, .. } => *albedo,
+			Material::Subsurface { albedo, .. } => *albedo,
+			Material::ThinFilm { base, .. }
+			| Material::Clearcoat { base, .. }
+			| Material::NormalMap { base, .. }
+			| Material::BumpMap { base, .. }
+			| Material::Cutout { base, .. }
+			| Material::Layered { base, .. } => base.albedo(),
+			// mixes are usually resolved with Material::choose before, this is the average
+			Material::Mix {
+				first,
+				second,
+				weight,
+			} => lerp(
+				first.albedo(),
+				second.albedo(),
+				weight.average().clamp(0.0, 1.0),
+			),
+		}
+	}
+
+	// materials with the same parameters share the same id
+	pub fn get_id(&self) -> u64 {
+		let mut hasher = DefaultHasher::new();
+		self.hash_parameters(&mut hasher);
+		hasher.finish()
+	}
+
+	fn hash_parameters(&self, hasher: &mut DefaultHasher) {
+		let hash_vec3 = |v: &Vec3, hasher: &mut DefaultHasher| {
+			v.x.to_bits().hash(hasher);
+			v.y.to_bits().hash(hasher);
+			v.z.to_bits().hash(hasher);
+		};
+		// textures are compared by identity
+		let hash_scalar_texture =
+			|texture: &ScalarTexture, hasher: &mut DefaultHasher| match texture {
+				ScalarTexture::Constant(value) => value.to_bits().hash(hasher),
+				ScalarTexture::Texture(texture) => Arc::as_ptr(texture).hash(hasher),
+			};
+		std::mem::discriminant(self).hash(hasher);
+		match self {
+			Material::Lambertain { albedo, emission } => {
+				hash_vec3(albedo, hasher);
+				emission.to_bits().hash(hasher);
+			}
+			Material::Metal { albedo, fuzz } => {
+				hash_vec3(albedo, hasher);
+				fuzz.to_bits().hash(hasher);
+			}
+			Material::Dielectric { ir } => ir.to_bits().hash(hasher),
+			Material::DispersiveDielectric { ior } => match ior {
+				Ior::Constant(ior) => ior.to_bits().hash(hasher),
+				Ior::Cauchy { a, b } => [a, b].map(|v| v.to_bits()).hash(hasher),
+				Ior::Sellmeier { b, c } => {
+					b.map(|v| v.to_bits()).hash(hasher);
+					c.map(|v| v.to_bits()).hash(hasher);
+				}
+			},
+			Material::ThinFilm {
+				base,
+				thickness,
+				ior,
+			} => {
+				base.hash_parameters(hasher);
+				thickness.to_bits().hash(hasher);
+				ior.to_bits().hash(hasher);
+			}
+			Material::Clearcoat {
+				base,
+				ior,
+				roughness,
+			} => {
+				base.hash_parameters(hasher);
+				ior.to_bits().hash(hasher);
+				roughness.to_bits().hash(hasher);
+			}
+			Material::Subsurface {
+				albedo,
+				mean_free_path,
+				ir,
+			} => {
+				hash_vec3(albedo, hasher);
+				hash_vec3(mean_free_path, hasher);
+				ir.to_bits().hash(hasher);
+			}
+			// textures are compared by identity
+			Material::NormalMap {
+				base,
+				map: texture,
+				strength,
+			}
+			| Material::BumpMap {
+				base,
+				height: texture,
+				strength,
+			} => {
+				base.hash_parameters(hasher);
+				Arc::as_ptr(texture).hash(hasher);
+				strength.to_bits().hash(hasher);
+			}
+			Material::Cutout { base, opacity } => {
+				base.hash_parameters(hasher);
+				hash_scalar_texture(opacity, hasher);
+			}
+			Material::Mix {
+				first,
+				second,
+				weight,
+			} => {
+				first.hash_parameters(hasher);
+				second.hash_parameters(hasher);
+				hash_scalar_texture(weight, hasher);
+			}
+			Material::Layered { coat, base } => {
+				coat.hash_parameters(hasher);
+				base.hash_parameters(hasher);
+			}
 		}
 	}
 
@@ -84,3 +803,159 @@ impl Material {
 		r0 + (1. - r0) * (1. - cosine).powf(5.)
 	}
 }
+
+// probability of the second material of a mix, textures can hold values outside of [0, 1]
+#[inline(always)]
+fn mix_weight(weight: &ScalarTexture, uv: (Scalar, Scalar)) -> Scalar {
+	weight.at(uv).clamp(0.0, 1.0)
+}
+
+#[inline(always)]
+fn lerp<T: Mul<Scalar, Output = T> + Add<Output = T>>(a: T, b: T, t: Scalar) -> T {
+	a * (1.0 - t) + b * t
+}
+
+// orthonormal tangent and bitangent around the normal, the tangent is the surface tangent made
+// perpendicular to it
+#[inline(always)]
+fn tangent_frame(normal: Vec3, surface_tangent: Vec3) -> (Vec3, Vec3) {
+	let tangent = surface_tangent - normal * normal.dot(surface_tangent);
+	let tangent = if tangent.near_zero() {
+		// the tangent is parallel to the normal, any perpendicular direction works
+		let axis = if normal.x.abs() < 0.9 {
+			Vec3::new(1.0, 0.0, 0.0)
+		} else {
+			Vec3::new(0.0, 1.0, 0.0)
+		};
+		normal.cross(axis)
+	} else {
+		tangent
+	}
+	.normalize();
+	(tangent, normal.cross(tangent))
+}
+
+// Airy reflectance of a film with ior n2 between the media n1 and n3 for unpolarized light,
+// cos_theta is measured in n1 and the wavelength and thickness are in nanometers
+fn thin_film_reflectance(
+	cos_theta: Scalar,
+	n1: Scalar,
+	n2: Scalar,
+	n3: Scalar,
+	thickness: Scalar,
+	wavelength: Scalar,
+) -> Scalar {
+	let sin2_theta1 = 1.0 - cos_theta * cos_theta;
+	let sin2_theta2 = (n1 / n2) * (n1 / n2) * sin2_theta1;
+	if sin2_theta2 >= 1.0 {
+		return 1.0;
+	}
+	let cos_theta2 = (1.0 - sin2_theta2).sqrt();
+	let sin2_theta3 = (n1 / n3) * (n1 / n3) * sin2_theta1;
+
+	// fresnel amplitude coefficients of both interfaces for s and p polarized light
+	let rs12 = (n1 * cos_theta - n2 * cos_theta2) / (n1 * cos_theta + n2 * cos_theta2);
+	let rp12 = (n2 * cos_theta - n1 * cos_theta2) / (n2 * cos_theta + n1 * cos_theta2);
+	let (rs23, rp23) = if sin2_theta3 >= 1.0 {
+		// total internal reflection at the bottom of the film
+		(1.0, 1.0)
+	} else {
+		let cos_theta3 = (1.0 - sin2_theta3).sqrt();
+		(
+			(n2 * cos_theta2 - n3 * cos_theta3) / (n2 * cos_theta2 + n3 * cos_theta3),
+			(n3 * cos_theta2 - n2 * cos_theta3) / (n3 * cos_theta2 + n2 * cos_theta3),
+		)
+	};
+
+	let cos_phase = (4.0 * PI * n2 * thickness * cos_theta2 / wavelength).cos();
+	let airy = |r12: Scalar, r23: Scalar| {
+		let cross = 2.0 * r12 * r23 * cos_phase;
+		(r12 * r12 + r23 * r23 + cross) / (1.0 + r12 * r12 * r23 * r23 + cross)
+	};
+	0.5 * (airy(rs12, rs23) + airy(rp12, rp23))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// reflectance of a bare interface at normal incidence
+	fn fresnel_normal(n1: Scalar, n2: Scalar) -> Scalar {
+		((n1 - n2) / (n1 + n2)).powi(2)
+	}
+
+	#[test]
+	fn thin_film_without_thickness_is_the_bare_interface() {
+		let reflectance = thin_film_reflectance(1.0, 1.0, 1.33, 1.5, 0.0, 550.0);
+		assert!((reflectance - fresnel_normal(1.0, 1.5)).abs() < 1e-12);
+	}
+
+	#[test]
+	fn thin_film_matching_the_outside_is_invisible() {
+		for thickness in [0.0, 100.0, 333.0, 1000.0] {
+			let reflectance = thin_film_reflectance(1.0, 1.0, 1.0, 1.5, thickness, 550.0);
+			assert!((reflectance - fresnel_normal(1.0, 1.5)).abs() < 1e-12);
+		}
+	}
+
+	#[test]
+	fn quarter_wave_film_cancels_the_reflection() {
+		let wavelength = 550.0;
+		let n2 = 1.5_f64.sqrt();
+		let thickness = wavelength / (4.0 * n2);
+		let reflectance = thin_film_reflectance(1.0, 1.0, n2, 1.5, thickness, wavelength);
+		assert!(reflectance < 1e-12, "{reflectance}");
+	}
+
+	#[test]
+	fn thin_film_reflectance_is_a_fraction() {
+		for cos_theta in [0.01, 0.3, 0.7, 1.0] {
+			for thickness in [0.0, 120.0, 480.0] {
+				for wavelength in [400.0, 550.0, 700.0] {
+					let reflectance =
+						thin_film_reflectance(cos_theta, 1.0, 1.33, 1.5, thickness, wavelength);
+					assert!((0.0..=1.0).contains(&reflectance), "{reflectance}");
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn thin_film_reflects_everything_after_total_internal_reflection() {
+		// 60° from glass into a film of air is past the critical angle
+		let reflectance = thin_film_reflectance(0.5, 1.5, 1.0, 1.5, 100.0, 550.0);
+		assert_eq!(reflectance, 1.0);
+	}
+
+	#[test]
+	fn subsurface_medium_looks_below_modifiers_and_coatings() {
+		let medium = Material::Subsurface {
+			albedo: Vec3::uniform(0.9),
+			mean_free_path: Vec3::uniform(0.2),
+			ir: 1.5,
+		};
+		let flat = Arc::new(ImageTexture {
+			width: 1,
+			height: 1,
+			pixels: vec![Vec3::new(0.5, 0.5, 1.0)],
+		});
+		let wrapped = [
+			medium.clone().with_thin_film(300.0, 1.33),
+			medium.clone().with_clearcoat(1.5, 0.0),
+			medium.clone().with_normal_map(flat.clone(), 1.0),
+			medium.clone().with_bump_map(flat, 1.0),
+			medium.clone().with_opacity(ScalarTexture::Constant(0.5)),
+			medium
+				.clone()
+				.with_coating(Material::Dielectric { ir: 1.5 })
+				.with_clearcoat(1.5, 0.1),
+		];
+		for material in wrapped {
+			assert_eq!(
+				material.subsurface_medium(),
+				Some((Vec3::uniform(0.9), Vec3::uniform(0.2)))
+			);
+		}
+		assert_eq!(Material::Dielectric { ir: 1.5 }.subsurface_medium(), None);
+	}
+}
@@ -10,7 +10,7 @@ pub fn radians(degrees: Scalar) -> Scalar {
 }
 
 #[inline(always)]
-pub fn random(min: Scalar, max: Scalar, rand: &mut rand::prelude::ThreadRng) -> Scalar {
+pub fn random(min: Scalar, max: Scalar, rand: &mut impl rand::Rng) -> Scalar {
 	rand.random_range(min..max)
 }
 
@@ -143,17 +143,37 @@ impl Vec3 {
 		self.z = new_z;
 	}
 
+	#[inline(always)]
+	pub fn max_component(&self) -> Scalar {
+		self.x.max(self.y).max(self.z)
+	}
+
+	#[inline(always)]
+	pub fn clamp(&self, min: Scalar, max: Scalar) -> Self {
+		Vec3::new(
+			self.x.clamp(min, max),
+			self.y.clamp(min, max),
+			self.z.clamp(min, max),
+		)
+	}
+
+	#[inline(always)]
+	pub fn luminance(&self) -> Scalar {
+		0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+	}
+
 	#[inline(always)]
 	pub fn linear_to_gamma(&self) -> Self {
+		// spectral samples can be slightly out of gamut
 		Vec3 {
-			x: self.x.sqrt(),
-			y: self.y.sqrt(),
-			z: self.z.sqrt(),
+			x: self.x.max(0.0).sqrt(),
+			y: self.y.max(0.0).sqrt(),
+			z: self.z.max(0.0).sqrt(),
 		}
 	}
 
 	#[inline(always)]
-	pub fn random(min: Scalar, max: Scalar, rand: &mut rand::prelude::ThreadRng) -> Self {
+	pub fn random(min: Scalar, max: Scalar, rand: &mut impl rand::Rng) -> Self {
 		Vec3 {
 			x: min + (max - min) * rand.random::<Scalar>(),
 			y: min + (max - min) * rand.random::<Scalar>(),
@@ -161,16 +181,29 @@ impl Vec3 {
 		}
 	}
 
+	// uniformly distributed on the unit sphere, sample from Sampler::get_2d
 	#[inline(always)]
-	pub fn random_unit_vector(rand: &mut rand::prelude::ThreadRng) -> Self {
-		let p = Self::random(-1.0, 1.0, rand);
-		p.normalize()
+	pub fn sample_unit_vector(sample: (Scalar, Scalar)) -> Self {
+		let z = 1.0 - 2.0 * sample.0;
+		let r = (1.0 - z * z).max(0.0).sqrt();
+		let phi = 2.0 * PI * sample.1;
+		Vec3::new(r * phi.cos(), r * phi.sin(), z)
 	}
 
+	// sample from Sampler::get_2d, uniform over the whole disk (Shirley-Chiu concentric mapping,
+	// keeps the stratification of the sample)
 	#[inline(always)]
-	pub fn random_in_unit_disk(rand: &mut rand::prelude::ThreadRng) -> Self {
-		let p = Vec3::new(random(-1.0, 1.0, rand), random(-1.0, 1.0, rand), 0.0);
-		p.normalize()
+	pub fn sample_in_unit_disk(sample: (Scalar, Scalar)) -> Self {
+		let (a, b) = (2.0 * sample.0 - 1.0, 2.0 * sample.1 - 1.0);
+		if a == 0.0 && b == 0.0 {
+			return Vec3::zero();
+		}
+		let (radius, theta) = if a.abs() > b.abs() {
+			(a, PI / 4.0 * (b / a))
+		} else {
+			(b, PI / 2.0 - PI / 4.0 * (a / b))
+		};
+		Vec3::new(radius * theta.cos(), radius * theta.sin(), 0.0)
 	}
 }
 
@@ -321,12 +354,23 @@ impl IndexMut<usize> for Vec3 {
 pub struct Ray {
 	pub origin: Vec3,
 	pub dir: Vec3,
+	// seconds after the shutter opened, for motion blur
+	pub time: Scalar,
 }
 
 impl Ray {
 	#[inline(always)]
 	pub fn new(origin: Vec3, dir: Vec3) -> Self {
-		Self { origin, dir }
+		Self {
+			origin,
+			dir,
+			time: 0.0,
+		}
+	}
+
+	#[inline(always)]
+	pub fn with_time(self, time: Scalar) -> Self {
+		Self { time, ..self }
 	}
 
 	#[inline(always)]
@@ -400,6 +444,29 @@ impl AABB {
 			),
 		)
 	}
+
+	#[inline(always)]
+	pub fn overlap(a: AABB, b: AABB) -> Self {
+		let min = Vec3::new(
+			Scalar::max(a.min.x, b.min.x),
+			Scalar::max(a.min.y, b.min.y),
+			Scalar::max(a.min.z, b.min.z),
+		);
+		let max = Vec3::new(
+			Scalar::min(a.max.x, b.max.x),
+			Scalar::min(a.max.y, b.max.y),
+			Scalar::min(a.max.z, b.max.z),
+		);
+		// disjoint boxes collapse to an empty box at the min corner
+		Self::new(
+			min,
+			Vec3::new(
+				Scalar::max(min.x, max.x),
+				Scalar::max(min.y, max.y),
+				Scalar::max(min.z, max.z),
+			),
+		)
+	}
 }
 
 impl Default for AABB {
@@ -0,0 +1,212 @@
+use crate::{
+	Camera, Projection, Ray, Sampler, Scalar, Vec3, camera::camera_basis, math::PI, radians,
+};
+
+// Parallel rays, objects keep their size regardless of the distance (architecture, technical renders)
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrthographicCamera {
+	pub origin: Vec3,
+	pixel00_loc: Vec3,
+	pixel_delta_x: Vec3,
+	pixel_delta_y: Vec3,
+	direction: Vec3,
+}
+
+impl OrthographicCamera {
+	// viewport_height is the height of the visible area in world units
+	pub fn new(
+		origin: Vec3,
+		direction: Vec3,
+		viewport_height: Scalar,
+		width: usize,
+		height: usize,
+	) -> Self {
+		let (u, v, w) = camera_basis(direction);
+		let viewport_width = viewport_height * (width as Scalar / height as Scalar);
+		let viewport_u = u * viewport_width;
+		let viewport_v = (-v) * viewport_height;
+		let pixel_delta_x = viewport_u / width as Scalar;
+		let pixel_delta_y = viewport_v / height as Scalar;
+		let viewport_upper_left = origin - viewport_u / 2.0 - viewport_v / 2.0;
+		Self {
+			origin,
+			pixel00_loc: viewport_upper_left + (pixel_delta_x + pixel_delta_y) * 0.5,
+			pixel_delta_x,
+			pixel_delta_y,
+			direction: -w,
+		}
+	}
+}
+
+impl Projection for OrthographicCamera {
+	fn get_ray(&self, x: Scalar, y: Scalar, _sampler: &mut dyn Sampler) -> Option<Ray> {
+		let ray_origin =
+			self.pixel00_loc + self.pixel_delta_x * (x - 0.5) + self.pixel_delta_y * (y - 0.5);
+		Some(Ray::new(ray_origin, self.direction))
+	}
+}
+
+// Equidistant fisheye: the angle to the view direction grows linearly with the distance to the
+// image center, fov is the angle across the image circle that fits the shorter image side
+#[derive(Debug, Clone, PartialEq)]
+pub struct FisheyeCamera {
+	pub origin: Vec3,
+	u: Vec3,
+	v: Vec3,
+	w: Vec3,
+	fov: Scalar,
+	width: usize,
+	height: usize,
+}
+
+impl FisheyeCamera {
+	// 180° covers a hemisphere (dome projections), up to 360°
+	pub const DEFAULT_FOV: Scalar = 180.0;
+
+	pub fn new(origin: Vec3, direction: Vec3, fov: Scalar, width: usize, height: usize) -> Self {
+		let (u, v, w) = camera_basis(direction);
+		Self {
+			origin,
+			u,
+			v,
+			w,
+			fov: radians(fov.min(360.0)),
+			width,
+			height,
+		}
+	}
+}
+
+impl Projection for FisheyeCamera {
+	fn get_ray(&self, x: Scalar, y: Scalar, _sampler: &mut dyn Sampler) -> Option<Ray> {
+		let radius = self.width.min(self.height) as Scalar / 2.0;
+		let dx = (x - self.width as Scalar / 2.0) / radius;
+		let dy = (y - self.height as Scalar / 2.0) / radius;
+		let r = (dx * dx + dy * dy).sqrt();
+		if r > 1.0 {
+			return None;
+		}
+		let theta = r * self.fov / 2.0;
+		let (sin_phi, cos_phi) = if r > 0.0 {
+			(dy / r, dx / r)
+		} else {
+			(0.0, 1.0)
+		};
+		let dir = -self.w * theta.cos() + (self.u * cos_phi - self.v * sin_phi) * theta.sin();
+		Some(Ray::new(self.origin, dir))
+	}
+}
+
+// Full spherical latitude-longitude panorama, the view direction is in the image center,
+// the image should be twice as wide as high
+#[derive(Debug, Clone, PartialEq)]
+pub struct EquirectangularCamera {
+	pub origin: Vec3,
+	u: Vec3,
+	v: Vec3,
+	w: Vec3,
+	width: usize,
+	height: usize,
+}
+
+impl EquirectangularCamera {
+	pub fn new(origin: Vec3, direction: Vec3, width: usize, height: usize) -> Self {
+		let (u, v, w) = camera_basis(direction);
+		Self {
+			origin,
+			u,
+			v,
+			w,
+			width,
+			height,
+		}
+	}
+}
+
+impl Projection for EquirectangularCamera {
+	fn get_ray(&self, x: Scalar, y: Scalar, _sampler: &mut dyn Sampler) -> Option<Ray> {
+		let longitude = (x / self.width as Scalar - 0.5) * 2.0 * PI;
+		let latitude = (0.5 - y / self.height as Scalar) * PI;
+		let horizontal = -self.w * longitude.cos() + self.u * longitude.sin();
+		let dir = horizontal * latitude.cos() + self.v * latitude.sin();
+		Some(Ray::new(self.origin, dir))
+	}
+}
+
+// Where a camera of any projection kind looks from and what it sees, see ProjectionKind::create
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProjectionSettings {
+	pub origin: Vec3,
+	pub direction: Vec3,
+	// vertical, in degrees
+	pub fov: Scalar,
+	pub focus_dist: Scalar,
+	pub defocus_angle: Scalar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectionKind {
+	Perspective,
+	Orthographic,
+	Fisheye,
+	Equirectangular,
+}
+
+impl ProjectionKind {
+	pub const NAMES: [&'static str; 4] = ["perspective", "orthographic", "fisheye", "equirect"];
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"perspective" => Some(Self::Perspective),
+			"orthographic" => Some(Self::Orthographic),
+			"fisheye" => Some(Self::Fisheye),
+			"equirect" => Some(Self::Equirectangular),
+			_ => None,
+		}
+	}
+
+	// the orthographic camera shows the same area as the perspective one does at focus_dist,
+	// fisheye and equirectangular cameras ignore the fov and defocus
+	pub fn create(
+		&self,
+		settings: &ProjectionSettings,
+		width: usize,
+		height: usize,
+	) -> Box<dyn Projection> {
+		let ProjectionSettings {
+			origin,
+			direction,
+			fov,
+			focus_dist,
+			defocus_angle,
+		} = *settings;
+		match self {
+			Self::Perspective => Box::new(Camera::new(
+				origin,
+				direction,
+				fov,
+				focus_dist,
+				defocus_angle,
+				width,
+				height,
+			)),
+			Self::Orthographic => Box::new(OrthographicCamera::new(
+				origin,
+				direction,
+				2.0 * focus_dist * (radians(fov) / 2.0).tan(),
+				width,
+				height,
+			)),
+			Self::Fisheye => Box::new(FisheyeCamera::new(
+				origin,
+				direction,
+				FisheyeCamera::DEFAULT_FOV,
+				width,
+				height,
+			)),
+			Self::Equirectangular => {
+				Box::new(EquirectangularCamera::new(origin, direction, width, height))
+			}
+		}
+	}
+}
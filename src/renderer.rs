@@ -1,40 +1,316 @@
-use crate::{BVH, Camera, Ray, Scalar, Scene, Vec3};
+use crate::{
+	Aovs, BVH, Material, Projection, Ray, RayHit, SampledSpectrum, SampledWavelengths, Sampler,
+	Scalar, Scattered, Scene, SurfaceAovs, Vec3, subsurface::random_walk,
+};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+	// bounces before russian roulette starts terminating paths
+	pub min_depth: u32,
+	// absolute safety cap for the path length
+	pub max_depth: u32,
+	// clamps the light gathered after the first bounce of every sample, None disables clamping
+	pub max_indirect_radiance: Option<Scalar>,
+	// specular surfaces hit after a diffuse bounce are roughened to at least this roughness,
+	// None disables path regularization
+	pub regularization: Option<Scalar>,
+	// traces hero wavelength samples instead of rgb, needed for dispersion but slower
+	pub spectral: bool,
+}
+
+impl RenderSettings {
+	pub const DEFAULT_MIN_DEPTH: u32 = 3;
+	pub const DEFAULT_MAX_DEPTH: u32 = 64;
+
+	// removes fireflies from tiny bright emitters at the cost of a slightly darker image
+	#[inline(always)]
+	fn clamp_radiance(&self, bounce: u32, light: Vec3) -> Vec3 {
+		match self.max_indirect_radiance {
+			Some(max_radiance) if bounce > 0 && light.max_component() > max_radiance => {
+				light * (max_radiance / light.max_component())
+			}
+			_ => light,
+		}
+	}
+
+	// refractions into subsurface materials continue with a random walk until the path leaves again
+	#[inline(always)]
+	fn scatter(
+		&self,
+		material: &Material,
+		ray: &Ray,
+		hit: &RayHit,
+		bvh: &BVH,
+		after_diffuse_bounce: bool,
+		sampler: &mut dyn Sampler,
+	) -> Option<Scattered> {
+		let scattered = match self.regularization {
+			Some(min_roughness) if after_diffuse_bounce => {
+				material.scatter_regularized(ray, hit, min_roughness, sampler)
+			}
+			_ => material.scatter(ray, hit, sampler),
+		}?;
+		// a perturbed shading normal can send the path through the actual surface, which would
+		// leak light from the other side
+		let shading_side = scattered.scattered.dir.dot(hit.normal) > 0.0;
+		let geometric_side = scattered.scattered.dir.dot(hit.geometric_normal) > 0.0;
+		if shading_side != geometric_side {
+			return None;
+		}
+		match material.subsurface_medium() {
+			Some((albedo, mean_free_path)) if scattered.scattered.dir.dot(hit.normal) < 0.0 => {
+				let walk = random_walk(scattered.scattered, bvh, albedo, mean_free_path, sampler)?;
+				Some(Scattered {
+					attenuation: scattered.attenuation * walk.attenuation,
+					scattered: walk.scattered,
+				})
+			}
+			_ => Some(scattered),
+		}
+	}
+}
+
+impl Default for RenderSettings {
+	fn default() -> Self {
+		Self {
+			min_depth: Self::DEFAULT_MIN_DEPTH,
+			max_depth: Self::DEFAULT_MAX_DEPTH,
+			max_indirect_radiance: None,
+			regularization: None,
+			spectral: false,
+		}
+	}
+}
+
+// what a path carries besides its ray: the throughput and whatever its materials are evaluated at
+trait PathThroughput {
+	// light arriving at the current vertex as it reaches the camera, in linear rgb
+	fn weight(&self, light: Vec3) -> Vec3;
+	// the material the path scatters with at the current vertex
+	fn scattering_material<'a>(&mut self, material: &'a Material) -> Cow<'a, Material>;
+	fn attenuate(&mut self, attenuation: Vec3);
+	fn max_component(&self) -> Scalar;
+	fn divide(&mut self, divisor: Scalar);
+}
+
+struct RgbThroughput(Vec3);
+
+impl PathThroughput for RgbThroughput {
+	#[inline(always)]
+	fn weight(&self, light: Vec3) -> Vec3 {
+		light * self.0
+	}
+
+	#[inline(always)]
+	fn scattering_material<'a>(&mut self, material: &'a Material) -> Cow<'a, Material> {
+		Cow::Borrowed(material)
+	}
+
+	#[inline(always)]
+	fn attenuate(&mut self, attenuation: Vec3) {
+		self.0 = self.0 * attenuation;
+	}
+
+	#[inline(always)]
+	fn max_component(&self) -> Scalar {
+		self.0.max_component()
+	}
+
+	#[inline(always)]
+	fn divide(&mut self, divisor: Scalar) {
+		self.0 = self.0 / divisor;
+	}
+}
+
+// a spectrum for a few wavelengths instead of rgb, every contribution is converted back to linear
+// rgb right away
+struct SpectralThroughput {
+	wavelengths: SampledWavelengths,
+	spectrum: SampledSpectrum,
+}
+
+impl PathThroughput for SpectralThroughput {
+	#[inline(always)]
+	fn weight(&self, light: Vec3) -> Vec3 {
+		let light = SampledSpectrum::from_rgb(light, &self.wavelengths);
+		(self.spectrum * light).to_rgb(&self.wavelengths)
+	}
+
+	#[inline(always)]
+	fn scattering_material<'a>(&mut self, material: &'a Material) -> Cow<'a, Material> {
+		if material.is_dispersive() {
+			self.wavelengths.terminate_secondary();
+		}
+		material.at_wavelength(self.wavelengths.hero())
+	}
+
+	#[inline(always)]
+	fn attenuate(&mut self, attenuation: Vec3) {
+		self.spectrum = self.spectrum * SampledSpectrum::from_rgb(attenuation, &self.wavelengths);
+	}
+
+	#[inline(always)]
+	fn max_component(&self) -> Scalar {
+		self.spectrum.max_value()
+	}
+
+	#[inline(always)]
+	fn divide(&mut self, divisor: Scalar) {
+		self.spectrum = self.spectrum / divisor;
+	}
+}
 
 fn ray_color(
-	ray: &Ray,
+	ray: Ray,
 	bvh: &BVH,
-	contribution: &mut Vec3,
-	depth: i32,
-	rand: &mut rand::prelude::ThreadRng,
+	settings: &RenderSettings,
+	sampler: &mut dyn Sampler,
+	aovs: Option<&mut Aovs>,
 ) -> Vec3 {
-	if depth <= 0 {
-		return Vec3::zero();
+	if settings.spectral {
+		let throughput = SpectralThroughput {
+			wavelengths: SampledWavelengths::sample(sampler.get_1d()),
+			spectrum: SampledSpectrum::uniform(1.0),
+		};
+		trace_path(ray, bvh, settings, sampler, aovs, throughput)
+	} else {
+		trace_path(
+			ray,
+			bvh,
+			settings,
+			sampler,
+			aovs,
+			RgbThroughput(Vec3::one()),
+		)
 	}
+}
+
+fn trace_path(
+	mut ray: Ray,
+	bvh: &BVH,
+	settings: &RenderSettings,
+	sampler: &mut dyn Sampler,
+	mut aovs: Option<&mut Aovs>,
+	mut throughput: impl PathThroughput,
+) -> Vec3 {
+	let mut light = Vec3::zero();
+	let mut after_diffuse_bounce = false;
+
+	for bounce in 0..settings.max_depth {
+		let Some(mut hit) = bvh.trace(&ray, sampler) else {
+			let sky_color =
+				settings.clamp_radiance(bounce, throughput.weight(Scene::get_sky_color(ray.dir)));
+			if let Some(aovs) = aovs.as_deref_mut() {
+				if bounce == 0 {
+					aovs.albedo = sky_color;
+				}
+				aovs.add_light(bounce, sky_color);
+			}
+			light = light + sky_color;
+			break;
+		};
 
-	if let Some(hit) = bvh.trace(ray) {
-		if let Some(scattered) = hit.material.scatter(ray, &hit, rand) {
-			*contribution = (*contribution) * scattered.attenuation;
-			scattered.attenuation
-				* ray_color(&scattered.scattered, bvh, contribution, depth - 1, rand)
-				+ hit.material.emission_color()
-		} else {
-			Vec3::zero()
+		let material = hit.material.choose(hit.uv, sampler);
+		hit.material = &material;
+		prepare_hit(&ray, &mut hit);
+
+		if let Some(aovs) = aovs.as_deref_mut()
+			&& bounce == 0
+		{
+			record_surface_aovs(aovs, &ray, &hit);
 		}
-	} else {
-		Scene::get_sky_color(ray.dir) * (*contribution)
+
+		let emission =
+			settings.clamp_radiance(bounce, throughput.weight(hit.material.emission_color()));
+		if let Some(aovs) = aovs.as_deref_mut() {
+			aovs.add_light(bounce, emission);
+		}
+		light = light + emission;
+
+		let material = throughput.scattering_material(hit.material);
+		let Some(scattered) =
+			settings.scatter(&material, &ray, &hit, bvh, after_diffuse_bounce, sampler)
+		else {
+			break;
+		};
+		after_diffuse_bounce |= material.roughness() >= 1.0;
+		throughput.attenuate(scattered.attenuation);
+
+		// russian roulette: paths that can't contribute much anymore get terminated randomly,
+		// surviving paths are weighted up to keep the result unbiased
+		if bounce + 1 >= settings.min_depth {
+			let survival_probability = throughput.max_component().min(1.0);
+			if sampler.get_1d() >= survival_probability {
+				break;
+			}
+			throughput.divide(survival_probability);
+		}
+
+		// materials don't know about time, the path stays at the time of its camera ray
+		ray = scattered.scattered.with_time(ray.time);
 	}
+
+	light
+}
+
+// replaces the normal of the hit with the normal/bump mapped one of its (already chosen) material,
+// which is bent towards the viewer when it would face away from it
+#[inline(always)]
+fn prepare_hit(ray: &Ray, hit: &mut RayHit) {
+	const MIN_COS: Scalar = 0.01;
+
+	let normal = hit.material.shading_normal(hit);
+	let view = -ray.dir.normalize();
+	let cos = normal.dot(view);
+	hit.normal = if cos < MIN_COS {
+		(normal + view * (MIN_COS - cos)).normalize()
+	} else {
+		normal
+	};
+}
+
+#[inline(always)]
+fn record_surface_aovs(aovs: &mut Aovs, ray: &Ray, hit: &RayHit) {
+	aovs.surface = Some(SurfaceAovs {
+		depth: (hit.point - ray.origin).length(),
+		position: hit.point,
+		normal: hit.normal,
+		object_id: hit.object_id,
+		material_id: hit.material.get_id(),
+	});
+	aovs.albedo = hit.material.albedo();
 }
 
 // output color is in linear color space -> convert to gamma with Vec3::linear_to_gamma
 pub fn render(
 	x: Scalar,
 	y: Scalar,
-	camera: &Camera,
+	camera: &dyn Projection,
 	bvh: &BVH,
-	max_depth: i32,
-	rand: &mut rand::prelude::ThreadRng,
+	settings: &RenderSettings,
+	sampler: &mut dyn Sampler,
 ) -> Vec3 {
-	let ray = camera.get_ray(x, y, rand);
-	let mut contribution = Vec3::one();
-	ray_color(&ray, bvh, &mut contribution, max_depth, rand)
+	let Some(ray) = camera.get_ray(x, y, sampler) else {
+		return Vec3::zero();
+	};
+	ray_color(ray, bvh, settings, sampler, None) * camera.film_scale()
+}
+
+// same as render, but also returns the aovs of the sample
+pub fn render_with_aovs(
+	x: Scalar,
+	y: Scalar,
+	camera: &dyn Projection,
+	bvh: &BVH,
+	settings: &RenderSettings,
+	sampler: &mut dyn Sampler,
+) -> (Vec3, Aovs) {
+	let mut aovs = Aovs::new();
+	let Some(ray) = camera.get_ray(x, y, sampler) else {
+		return (Vec3::zero(), aovs);
+	};
+	let color = ray_color(ray, bvh, settings, sampler, Some(&mut aovs));
+	aovs.scale_light(camera.film_scale());
+	(color * camera.film_scale(), aovs)
 }
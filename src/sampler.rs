@@ -0,0 +1,579 @@
+use crate::Scalar;
+use rand::RngExt;
+use std::sync::OnceLock;
+
+// Source of all random numbers of a camera sample. Every call to get_1d / get_2d uses the next
+// dimension, so the n-th random number of every sample of a pixel comes from the same
+// (low discrepancy) sequence.
+pub trait Sampler {
+	// has to be called before every camera sample, resets the dimension to 0
+	fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32);
+
+	// returns 0.0 .. 1.0
+	fn get_1d(&mut self) -> Scalar;
+
+	// returns 0.0 .. 1.0 for both components
+	fn get_2d(&mut self) -> (Scalar, Scalar);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplerKind {
+	Independent,
+	// needs the sample count up front, samples after that are independent
+	Stratified { samples_per_pixel: u32 },
+	Halton,
+	Sobol,
+	BlueNoise,
+}
+
+impl SamplerKind {
+	pub const NAMES: [&'static str; 5] =
+		["independent", "stratified", "halton", "sobol", "blue-noise"];
+
+	pub fn from_name(name: &str, samples_per_pixel: u32) -> Option<Self> {
+		match name {
+			"independent" => Some(Self::Independent),
+			"stratified" => Some(Self::Stratified { samples_per_pixel }),
+			"halton" => Some(Self::Halton),
+			"sobol" => Some(Self::Sobol),
+			"blue-noise" => Some(Self::BlueNoise),
+			_ => None,
+		}
+	}
+
+	pub fn create(&self) -> Box<dyn Sampler> {
+		match self {
+			Self::Independent => Box::new(IndependentSampler::new()),
+			Self::Stratified { samples_per_pixel } => {
+				Box::new(StratifiedSampler::new(*samples_per_pixel))
+			}
+			Self::Halton => Box::new(HaltonSampler::new()),
+			Self::Sobol => Box::new(SobolSampler::new()),
+			Self::BlueNoise => Box::new(BlueNoiseSampler::new()),
+		}
+	}
+}
+
+// Uniform random numbers without any correlation between samples
+pub struct IndependentSampler {
+	rand: rand::prelude::ThreadRng,
+}
+
+impl IndependentSampler {
+	pub fn new() -> Self {
+		Self { rand: rand::rng() }
+	}
+}
+
+impl Default for IndependentSampler {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Sampler for IndependentSampler {
+	fn start_pixel_sample(&mut self, _x: usize, _y: usize, _sample_index: u32) {}
+
+	#[inline(always)]
+	fn get_1d(&mut self) -> Scalar {
+		self.rand.random::<Scalar>()
+	}
+
+	#[inline(always)]
+	fn get_2d(&mut self) -> (Scalar, Scalar) {
+		(self.rand.random::<Scalar>(), self.rand.random::<Scalar>())
+	}
+}
+
+// Jittered strata, shuffled independently for every pixel and dimension
+#[derive(Debug, Clone)]
+pub struct StratifiedSampler {
+	samples_per_pixel: u32,
+	pixel_seed: u32,
+	sample_index: u32,
+	dimension: u32,
+}
+
+impl StratifiedSampler {
+	pub fn new(samples_per_pixel: u32) -> Self {
+		Self {
+			samples_per_pixel: samples_per_pixel.max(1),
+			pixel_seed: 0,
+			sample_index: 0,
+			dimension: 0,
+		}
+	}
+
+	#[inline(always)]
+	fn next_seed(&mut self) -> u32 {
+		let seed = hash_combine(self.pixel_seed, self.dimension);
+		self.dimension += 1;
+		seed
+	}
+}
+
+impl Sampler for StratifiedSampler {
+	fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+		self.pixel_seed = pixel_seed(x, y);
+		self.sample_index = sample_index;
+		self.dimension = 0;
+	}
+
+	fn get_1d(&mut self) -> Scalar {
+		let seed = self.next_seed();
+		let jitter = to_unit(hash_combine(seed, self.sample_index));
+		let strata = self.samples_per_pixel;
+		if self.sample_index < strata {
+			let stratum = permute(self.sample_index, strata, seed);
+			(stratum as Scalar + jitter) / strata as Scalar
+		} else {
+			jitter
+		}
+	}
+
+	fn get_2d(&mut self) -> (Scalar, Scalar) {
+		let seed = self.next_seed();
+		let jitter = (
+			to_unit(hash_combine(seed, 2 * self.sample_index)),
+			to_unit(hash_combine(seed, 2 * self.sample_index + 1)),
+		);
+		let strata_x = (self.samples_per_pixel as Scalar).sqrt() as u32;
+		let strata_y = self.samples_per_pixel / strata_x;
+		let strata = strata_x * strata_y;
+		if self.sample_index < strata {
+			let stratum = permute(self.sample_index, strata, seed);
+			(
+				((stratum % strata_x) as Scalar + jitter.0) / strata_x as Scalar,
+				((stratum / strata_x) as Scalar + jitter.1) / strata_y as Scalar,
+			)
+		} else {
+			jitter
+		}
+	}
+}
+
+// Halton sequence with a random rotation (Cranley-Patterson) per pixel and dimension
+#[derive(Debug, Clone, Default)]
+pub struct HaltonSampler {
+	pixel_seed: u32,
+	sample_index: u32,
+	dimension: u32,
+}
+
+impl HaltonSampler {
+	const PRIMES: [u32; 32] = [
+		2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+		97, 101, 103, 107, 109, 113, 127, 131,
+	];
+
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+impl Sampler for HaltonSampler {
+	fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+		self.pixel_seed = pixel_seed(x, y);
+		self.sample_index = sample_index;
+		self.dimension = 0;
+	}
+
+	fn get_1d(&mut self) -> Scalar {
+		let seed = hash_combine(self.pixel_seed, self.dimension);
+		let value = match Self::PRIMES.get(self.dimension as usize) {
+			Some(base) => radical_inverse(*base, self.sample_index),
+			// running out of primes, just use random numbers
+			None => to_unit(hash_combine(seed, self.sample_index)),
+		};
+		self.dimension += 1;
+		(value + to_unit(seed)).fract()
+	}
+
+	fn get_2d(&mut self) -> (Scalar, Scalar) {
+		(self.get_1d(), self.get_1d())
+	}
+}
+
+// Owen scrambled sobol, every dimension pair is shuffled and scrambled differently
+// (Burley 2020, "Practical Hash-based Owen Scrambling")
+#[derive(Debug, Clone, Default)]
+pub struct SobolSampler {
+	pixel_seed: u32,
+	sample_index: u32,
+	dimension: u32,
+}
+
+impl SobolSampler {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+impl Sampler for SobolSampler {
+	fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+		self.pixel_seed = pixel_seed(x, y);
+		self.sample_index = sample_index;
+		self.dimension = 0;
+	}
+
+	fn get_1d(&mut self) -> Scalar {
+		let seed = hash_combine(self.pixel_seed, self.dimension);
+		self.dimension += 1;
+		to_unit(shuffled_scrambled_sobol_1d(self.sample_index, seed))
+	}
+
+	fn get_2d(&mut self) -> (Scalar, Scalar) {
+		let seed = hash_combine(self.pixel_seed, self.dimension);
+		self.dimension += 2;
+		let (x, y) = shuffled_scrambled_sobol_2d(self.sample_index, seed);
+		(to_unit(x), to_unit(y))
+	}
+}
+
+// All pixels share the same scrambled sobol sequence, rotated by a blue noise mask per pixel.
+// This distributes the remaining error as blue noise over the image, which is less visible.
+#[derive(Debug, Clone)]
+pub struct BlueNoiseSampler {
+	x: usize,
+	y: usize,
+	sample_index: u32,
+	dimension: u32,
+	mask: &'static [Scalar],
+}
+
+impl BlueNoiseSampler {
+	pub fn new() -> Self {
+		Self {
+			x: 0,
+			y: 0,
+			sample_index: 0,
+			dimension: 0,
+			mask: blue_noise_mask(),
+		}
+	}
+
+	// the mask is shifted by a different offset for every dimension to decorrelate them
+	#[inline(always)]
+	fn rotation(&self, dimension: u32) -> Scalar {
+		let offset = hash(dimension) as usize;
+		let x = (self.x + offset) % BLUE_NOISE_SIZE;
+		let y = (self.y + (offset >> 16)) % BLUE_NOISE_SIZE;
+		self.mask[y * BLUE_NOISE_SIZE + x]
+	}
+}
+
+impl Default for BlueNoiseSampler {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Sampler for BlueNoiseSampler {
+	fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+		self.x = x;
+		self.y = y;
+		self.sample_index = sample_index;
+		self.dimension = 0;
+	}
+
+	fn get_1d(&mut self) -> Scalar {
+		let value = to_unit(shuffled_scrambled_sobol_1d(
+			self.sample_index,
+			hash(self.dimension),
+		));
+		let rotation = self.rotation(self.dimension);
+		self.dimension += 1;
+		(value + rotation).fract()
+	}
+
+	fn get_2d(&mut self) -> (Scalar, Scalar) {
+		let (x, y) = shuffled_scrambled_sobol_2d(self.sample_index, hash(self.dimension));
+		let rotation = (
+			self.rotation(self.dimension),
+			self.rotation(self.dimension + 1),
+		);
+		self.dimension += 2;
+		(
+			(to_unit(x) + rotation.0).fract(),
+			(to_unit(y) + rotation.1).fract(),
+		)
+	}
+}
+
+// same hash as the gpu shader
+#[inline(always)]
+fn hash(seed: u32) -> u32 {
+	let mut state = seed.wrapping_mul(747796405).wrapping_add(2891336453);
+	state ^= state >> 17;
+	state = state.wrapping_mul(2246822519);
+	state ^= state >> 15;
+	state = state.wrapping_mul(3266489917);
+	state ^= state >> 16;
+	state
+}
+
+#[inline(always)]
+fn hash_combine(seed: u32, value: u32) -> u32 {
+	hash(seed ^ hash(value))
+}
+
+#[inline(always)]
+fn pixel_seed(x: usize, y: usize) -> u32 {
+	hash_combine(hash(x as u32), y as u32)
+}
+
+// maps to 0.0 .. 1.0 (exclusive)
+#[inline(always)]
+fn to_unit(value: u32) -> Scalar {
+	value as Scalar / 4294967296.0
+}
+
+#[inline(always)]
+fn radical_inverse(base: u32, mut index: u32) -> Scalar {
+	let inverse_base = 1.0 / base as Scalar;
+	let mut factor = inverse_base;
+	let mut result = 0.0;
+	while index > 0 {
+		result += (index % base) as Scalar * factor;
+		index /= base;
+		factor *= inverse_base;
+	}
+	result
+}
+
+// returns a random element of a permutation of 0..length, from Kensler 2013
+// "Correlated Multi-Jittered Sampling"
+#[inline(always)]
+fn permute(mut i: u32, length: u32, seed: u32) -> u32 {
+	let mut w = length - 1;
+	w |= w >> 1;
+	w |= w >> 2;
+	w |= w >> 4;
+	w |= w >> 8;
+	w |= w >> 16;
+	loop {
+		i ^= seed;
+		i = i.wrapping_mul(0xe170893d);
+		i ^= seed >> 16;
+		i ^= (i & w) >> 4;
+		i ^= seed >> 8;
+		i = i.wrapping_mul(0x0929eb3f);
+		i ^= seed >> 23;
+		i ^= (i & w) >> 1;
+		i = i.wrapping_mul(1 | (seed >> 27));
+		i = i.wrapping_mul(0x6935fa69);
+		i ^= (i & w) >> 11;
+		i = i.wrapping_mul(0x74dcb303);
+		i ^= (i & w) >> 2;
+		i = i.wrapping_mul(0x9e501cc3);
+		i ^= (i & w) >> 2;
+		i = i.wrapping_mul(0xc860a3df);
+		i &= w;
+		i ^= i >> 5;
+		if i < length {
+			break;
+		}
+	}
+	(i.wrapping_add(seed)) % length
+}
+
+#[inline(always)]
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+	x = x.wrapping_add(seed);
+	x ^= x.wrapping_mul(0x6c50b47c);
+	x ^= x.wrapping_mul(0xb82f1e52);
+	x ^= x.wrapping_mul(0xc7afe638);
+	x ^= x.wrapping_mul(0x8d22f6e6);
+	x
+}
+
+#[inline(always)]
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+	laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+// first two sobol dimensions, the first one is the van der corput sequence
+#[inline(always)]
+fn sobol_2d(index: u32) -> (u32, u32) {
+	let mut x = 0;
+	let mut y = 0;
+	let mut direction: u32 = 1 << 31;
+	for bit in 0..32 {
+		if (index >> bit) & 1 == 1 {
+			x ^= 1 << (31 - bit);
+			y ^= direction;
+		}
+		direction ^= direction >> 1;
+	}
+	(x, y)
+}
+
+#[inline(always)]
+fn shuffled_scrambled_sobol_1d(index: u32, seed: u32) -> u32 {
+	let index = nested_uniform_scramble(index, seed);
+	nested_uniform_scramble(index.reverse_bits(), hash(seed))
+}
+
+#[inline(always)]
+fn shuffled_scrambled_sobol_2d(index: u32, seed: u32) -> (u32, u32) {
+	let index = nested_uniform_scramble(index, seed);
+	let (x, y) = sobol_2d(index);
+	let seed = hash(seed);
+	let x = nested_uniform_scramble(x, seed);
+	let seed = hash(seed);
+	let y = nested_uniform_scramble(y, seed);
+	(x, y)
+}
+
+const BLUE_NOISE_SIZE: usize = 64;
+
+fn blue_noise_mask() -> &'static [Scalar] {
+	static MASK: OnceLock<Vec<Scalar>> = OnceLock::new();
+	MASK.get_or_init(generate_blue_noise_mask)
+}
+
+// void-and-cluster (Ulichney 1993) without the initial pattern: always fills the largest void,
+// the order of the filled pixels is the value of the mask
+fn generate_blue_noise_mask() -> Vec<Scalar> {
+	const SIGMA: Scalar = 1.5;
+	let size = BLUE_NOISE_SIZE;
+	let pixel_count = size * size;
+
+	// gaussian energy around a filled pixel, wraps around the edges
+	let kernel: Vec<Scalar> = (0..pixel_count)
+		.map(|i| {
+			let dx = (i % size).min(size - i % size) as Scalar;
+			let dy = (i / size).min(size - i / size) as Scalar;
+			(-(dx * dx + dy * dy) / (2.0 * SIGMA * SIGMA)).exp()
+		})
+		.collect();
+
+	let mut energy = vec![0.0; pixel_count];
+	let mut filled = vec![false; pixel_count];
+	let mut mask = vec![0.0; pixel_count];
+	for rank in 0..pixel_count {
+		let void = (0..pixel_count)
+			.filter(|i| !filled[*i])
+			.min_by(|a, b| energy[*a].partial_cmp(&energy[*b]).unwrap())
+			.unwrap();
+		filled[void] = true;
+		mask[void] = (rank as Scalar + 0.5) / pixel_count as Scalar;
+
+		let (void_x, void_y) = (void % size, void / size);
+		for (i, energy) in energy.iter_mut().enumerate() {
+			let dx = (i % size + size - void_x) % size;
+			let dy = (i / size + size - void_y) % size;
+			*energy += kernel[dy * size + dx];
+		}
+	}
+	mask
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const KINDS: [SamplerKind; 5] = [
+		SamplerKind::Independent,
+		SamplerKind::Stratified {
+			samples_per_pixel: 16,
+		},
+		SamplerKind::Halton,
+		SamplerKind::Sobol,
+		SamplerKind::BlueNoise,
+	];
+
+	// index of the sample's stratum for every sample of a pixel in one dimension
+	fn strata_1d(sampler: &mut dyn Sampler, samples: u32, dimension: u32) -> Vec<usize> {
+		let mut strata: Vec<usize> = (0..samples)
+			.map(|index| {
+				sampler.start_pixel_sample(3, 7, index);
+				for _ in 0..dimension {
+					sampler.get_1d();
+				}
+				(sampler.get_1d() * samples as Scalar) as usize
+			})
+			.collect();
+		strata.sort_unstable();
+		strata
+	}
+
+	// index of the sample's cell in a grid_size x grid_size grid for every sample of a pixel
+	fn strata_2d(sampler: &mut dyn Sampler, grid_size: u32) -> Vec<usize> {
+		let mut strata: Vec<usize> = (0..grid_size * grid_size)
+			.map(|index| {
+				sampler.start_pixel_sample(3, 7, index);
+				let (x, y) = sampler.get_2d();
+				let cell = |value: Scalar| (value * grid_size as Scalar) as usize;
+				cell(y) * grid_size as usize + cell(x)
+			})
+			.collect();
+		strata.sort_unstable();
+		strata
+	}
+
+	#[test]
+	fn samples_are_in_the_unit_interval() {
+		for kind in KINDS {
+			let mut sampler = kind.create();
+			for (x, y) in [(0, 0), (1, 0), (17, 93), (1000, 1000)] {
+				for index in 0..64 {
+					sampler.start_pixel_sample(x, y, index);
+					for _ in 0..40 {
+						let value = sampler.get_1d();
+						assert!((0.0..1.0).contains(&value), "{kind:?}: {value}");
+						let (u, v) = sampler.get_2d();
+						assert!((0.0..1.0).contains(&u), "{kind:?}: {u}");
+						assert!((0.0..1.0).contains(&v), "{kind:?}: {v}");
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn stratified_sampler_has_one_sample_per_stratum() {
+		let mut sampler = StratifiedSampler::new(16);
+		for dimension in 0..4 {
+			assert_eq!(
+				strata_1d(&mut sampler, 16, dimension),
+				(0..16).collect::<Vec<_>>()
+			);
+		}
+		assert_eq!(strata_2d(&mut sampler, 4), (0..16).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn sobol_sampler_is_stratified_for_powers_of_two() {
+		let mut sampler = SobolSampler::new();
+		for samples in [4, 16, 64] {
+			for dimension in 0..4 {
+				assert_eq!(
+					strata_1d(&mut sampler, samples, dimension),
+					(0..samples as usize).collect::<Vec<_>>()
+				);
+			}
+		}
+		for grid_size in [2, 4, 8] {
+			assert_eq!(
+				strata_2d(&mut sampler, grid_size),
+				(0..(grid_size * grid_size) as usize).collect::<Vec<_>>()
+			);
+		}
+	}
+
+	#[test]
+	fn samples_depend_on_the_pixel() {
+		for kind in [
+			SamplerKind::Halton,
+			SamplerKind::Sobol,
+			SamplerKind::BlueNoise,
+		] {
+			let mut sampler = kind.create();
+			sampler.start_pixel_sample(0, 0, 0);
+			let first = sampler.get_2d();
+			sampler.start_pixel_sample(1, 0, 0);
+			assert_ne!(first, sampler.get_2d(), "{kind:?}");
+		}
+	}
+}
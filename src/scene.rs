@@ -1,7 +1,31 @@
-use crate::{AABB, Cube, Material, Ray, Scalar, Sphere, Vec3, random};
+use crate::{
+	AABB, Csg, CsgOperation, Cube, ImageTexture, Ior, Material, Moving, Ray, Scalar, ScalarTexture,
+	Sphere, Vec3, math::PI, random,
+};
+use rand::SeedableRng;
+use std::sync::Arc;
 
 pub trait Hittable {
-	fn hit(&self, ray: &Ray) -> Option<Scalar>;
+	fn hit(&self, ray: &Ray) -> Option<Hit<'_>>;
+}
+
+// Closest hit of a ray with an object
+#[derive(Clone, Copy)]
+pub struct Hit<'a> {
+	pub distance: Scalar,
+	// set by objects that already found the surface while looking for the hit (csg), the surface
+	// is then taken from here instead of asking the object again
+	pub crossing: Option<Crossing<'a>>,
+}
+
+impl Hit<'_> {
+	#[inline(always)]
+	pub fn at(distance: Scalar) -> Self {
+		Hit {
+			distance,
+			crossing: None,
+		}
+	}
 }
 
 pub trait Bounded {
@@ -12,39 +36,102 @@ pub trait Renderable {
 	// Returns normal and if it is front-/back-face
 	fn get_normal(&self, p: &Vec3, ray: &Ray) -> Vec3;
 
-	fn get_material(&self) -> Option<&Material>;
+	fn get_material(&self, p: &Vec3, ray: &Ray) -> Option<&Material>;
+
+	// Returns the texture coordinates and the tangent (direction of increasing u) at p
+	fn get_surface_frame(&self, p: &Vec3, ray: &Ray) -> ((Scalar, Scalar), Vec3);
+}
+
+// Closed volume that can report every part of a ray inside of it, used for csg
+pub trait Solid {
+	// Returns all spans of the ray (also behind the origin) that are inside, sorted by distance
+	fn get_spans(&self, ray: &Ray) -> Vec<Span<'_>>;
+}
+
+// Point where a ray crosses the surface of a solid
+#[derive(Clone, Copy)]
+pub struct Crossing<'a> {
+	pub distance: Scalar,
+	// always points out of the solid
+	pub normal: Vec3,
+	pub material: &'a Material,
+	// the primitive the surface belongs to and how far it was moved at the time of the ray
+	pub shape: &'a dyn Renderable,
+	pub offset: Vec3,
+}
+
+impl Crossing<'_> {
+	// texture coordinates and tangent of the primitive at p
+	#[inline(always)]
+	pub fn get_surface_frame(&self, p: &Vec3, ray: &Ray) -> ((Scalar, Scalar), Vec3) {
+		let local_ray = Ray {
+			origin: ray.origin - self.offset,
+			..*ray
+		};
+		self.shape
+			.get_surface_frame(&(*p - self.offset), &local_ray)
+	}
+}
+
+#[derive(Clone, Copy)]
+pub struct Span<'a> {
+	pub enter: Crossing<'a>,
+	pub exit: Crossing<'a>,
 }
 
 pub struct RayHit<'a> {
 	pub point: Vec3,
+	// shading normal, can be perturbed by normal or bump maps
 	pub normal: Vec3,
+	// normal of the actual surface, both normals face against the ray
+	pub geometric_normal: Vec3,
 	pub material: &'a Material,
 	pub front_face: bool,
+	// index of the hit object in the scene the BVH was built from
+	pub object_id: usize,
+	pub uv: (Scalar, Scalar),
+	pub tangent: Vec3,
 }
 
 impl<'a> RayHit<'a> {
-	pub fn new(point: Vec3, normal: Vec3, material: &'a Material, front_face: bool) -> Self {
+	pub fn new(
+		point: Vec3,
+		normal: Vec3,
+		material: &'a Material,
+		front_face: bool,
+		object_id: usize,
+		uv: (Scalar, Scalar),
+		tangent: Vec3,
+	) -> Self {
 		RayHit {
 			point,
 			normal,
+			geometric_normal: normal,
 			material,
 			front_face,
+			object_id,
+			uv,
+			tangent,
 		}
 	}
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub enum Object {
 	Sphere(Sphere),
 	Cube(Cube),
+	Csg(Box<Csg>),
+	Moving(Box<Moving>),
 }
 
 impl Hittable for Object {
 	#[inline(always)]
-	fn hit(&self, ray: &Ray) -> Option<Scalar> {
+	fn hit(&self, ray: &Ray) -> Option<Hit<'_>> {
 		match self {
 			Self::Sphere(sphere) => sphere.hit(ray),
 			Self::Cube(cube) => cube.hit(ray),
+			Self::Csg(csg) => csg.hit(ray),
+			Self::Moving(moving) => moving.hit(ray),
 		}
 	}
 }
@@ -55,6 +142,8 @@ impl Bounded for Object {
 		match self {
 			Self::Sphere(sphere) => sphere.get_aabb(),
 			Self::Cube(cube) => cube.get_aabb(),
+			Self::Csg(csg) => csg.get_aabb(),
+			Self::Moving(moving) => moving.get_aabb(),
 		}
 	}
 }
@@ -65,14 +154,40 @@ impl Renderable for Object {
 		match self {
 			Self::Sphere(sphere) => sphere.get_normal(p, _ray),
 			Self::Cube(cube) => cube.get_normal(p, _ray),
+			Self::Csg(csg) => csg.get_normal(p, _ray),
+			Self::Moving(moving) => moving.get_normal(p, _ray),
 		}
 	}
 
 	#[inline(always)]
-	fn get_material(&self) -> Option<&Material> {
+	fn get_material(&self, p: &Vec3, ray: &Ray) -> Option<&Material> {
 		match self {
-			Self::Sphere(sphere) => sphere.get_material(),
-			Self::Cube(cube) => cube.get_material(),
+			Self::Sphere(sphere) => sphere.get_material(p, ray),
+			Self::Cube(cube) => cube.get_material(p, ray),
+			Self::Csg(csg) => csg.get_material(p, ray),
+			Self::Moving(moving) => moving.get_material(p, ray),
+		}
+	}
+
+	#[inline(always)]
+	fn get_surface_frame(&self, p: &Vec3, ray: &Ray) -> ((Scalar, Scalar), Vec3) {
+		match self {
+			Self::Sphere(sphere) => sphere.get_surface_frame(p, ray),
+			Self::Cube(cube) => cube.get_surface_frame(p, ray),
+			Self::Csg(csg) => csg.get_surface_frame(p, ray),
+			Self::Moving(moving) => moving.get_surface_frame(p, ray),
+		}
+	}
+}
+
+impl Solid for Object {
+	#[inline(always)]
+	fn get_spans(&self, ray: &Ray) -> Vec<Span<'_>> {
+		match self {
+			Self::Sphere(sphere) => sphere.get_spans(ray),
+			Self::Cube(cube) => cube.get_spans(ray),
+			Self::Csg(csg) => csg.get_spans(ray),
+			Self::Moving(moving) => moving.get_spans(ray),
 		}
 	}
 }
@@ -83,6 +198,8 @@ pub struct Scene {
 }
 
 impl Scene {
+	const SAMPLE_SCENE_SEED: u64 = 42;
+
 	pub fn new(objects: Vec<Object>) -> Self {
 		Self { objects }
 	}
@@ -97,11 +214,16 @@ impl Scene {
 			.push(Object::Cube(Cube::new(center, size, material)));
 	}
 
+	pub fn add_csg(&mut self, operation: CsgOperation, left: Object, right: Object) {
+		self.objects
+			.push(Object::Csg(Box::new(Csg::new(operation, left, right))));
+	}
+
 	pub fn hit(&self, ray: &Ray) -> Option<(Scalar, &Object)> {
 		let mut closest_hit_distance = Scalar::MAX;
 		let mut closest_object: Option<&Object> = None;
 		for object in self.objects.iter() {
-			if let Some(t) = object.hit(ray)
+			if let Some(t) = object.hit(ray).map(|hit| hit.distance)
 				&& t > 0.001 && t < closest_hit_distance
 			{
 				closest_hit_distance = t;
@@ -145,14 +267,15 @@ impl Scene {
 			albedo: Vec3::new(0.8, 0.4, 0.2),
 			emission: 60.0,
 		};
-		scene.add_sphere(Vec3::new(0.0, 1.0, 0.0), 1.0, mat1);
+		scene.add_sphere(Vec3::new(0.0, 1.0, 0.0), 1.0, mat1.clone());
 		scene.add_sphere(Vec3::new(0.0, 1.0, 0.0), -0.98, mat1);
-		scene.add_sphere(Vec3::new(4.0, 1.0, 0.0), 1.0, mat2);
+		scene.add_sphere(Vec3::new(4.0, 1.0, 0.0), 1.0, mat2.clone());
 		scene.add_sphere(Vec3::new(-4.0, 1.0, 0.0), 1.0, mat3);
 		scene.add_sphere(Vec3::new(10000.0, 5000.0, 10000.0), 7500.0, sun_mat);
 		scene.add_cube(Vec3::new(-4.0, 0.5, 2.5), Vec3::uniform(0.8), mat2);
 
-		let mut rand = rand::rng();
+		// seeded, the scene has to be the same in every run to resume a render of it
+		let mut rand = rand::rngs::StdRng::seed_from_u64(Self::SAMPLE_SCENE_SEED);
 		for a in -11..11 {
 			for b in -11..11 {
 				let random_mat = random(0.0, 1.0, &mut rand);
@@ -172,9 +295,9 @@ impl Scene {
 							emission: random_mat,
 						};
 						if random(0.0, 1.0, &mut rand) > 0.5 {
-							scene.add_sphere(center, 0.2, material);
+							scene.add_sphere(center, 0.2, material.clone());
 						} else {
-							scene.add_cube(center, Vec3::uniform(0.2), material);
+							scene.add_cube(center, Vec3::uniform(0.2), material.clone());
 						}
 					} else if random_mat < 0.85 {
 						// metal
@@ -182,18 +305,18 @@ impl Scene {
 						let fuzz = random(0.0, 0.3, &mut rand);
 						let material = Material::Metal { albedo, fuzz };
 						if random(0.0, 1.0, &mut rand) > 0.5 {
-							scene.add_sphere(center, 0.2, material);
+							scene.add_sphere(center, 0.2, material.clone());
 						} else {
-							scene.add_cube(center, Vec3::uniform(0.2), material);
+							scene.add_cube(center, Vec3::uniform(0.2), material.clone());
 						}
 					} else {
 						// glass
 						let material = Material::Dielectric { ir: 1.5 };
 						if random(0.0, 1.0, &mut rand) > 0.5 {
-							scene.add_sphere(center, 0.2, material);
+							scene.add_sphere(center, 0.2, material.clone());
 							scene.add_sphere(center, -0.19, material)
 						} else {
-							scene.add_cube(center, Vec3::uniform(0.2), material);
+							scene.add_cube(center, Vec3::uniform(0.2), material.clone());
 							scene.add_cube(center, Vec3::uniform(-0.19), material);
 						}
 					}
@@ -203,6 +326,459 @@ impl Scene {
 
 		scene
 	}
+
+	pub fn create_csg_scene() -> Self {
+		let mut scene = Scene::new(Vec::new());
+
+		let material_ground = Material::Lambertain {
+			albedo: Vec3::new(0.5, 0.5, 0.5),
+			emission: 0.0,
+		};
+		scene.add_cube(
+			Vec3::new(0.0, -1000.0, 0.0),
+			Vec3::uniform(1000.0),
+			material_ground,
+		);
+
+		let glass = Material::Dielectric { ir: 1.5 };
+		let red = Material::Lambertain {
+			albedo: Vec3::new(0.8, 0.1, 0.1),
+			emission: 0.0,
+		};
+		let metal = Material::Metal {
+			albedo: Vec3::new(0.7, 0.6, 0.5),
+			fuzz: 0.05,
+		};
+		let sun_mat = Material::Lambertain {
+			albedo: Vec3::new(0.8, 0.4, 0.2),
+			emission: 60.0,
+		};
+
+		// hollow glass ball
+		scene.add_csg(
+			CsgOperation::Difference,
+			Object::Sphere(Sphere::new(Vec3::new(-3.0, 1.0, 0.0), 1.0, glass.clone())),
+			Object::Sphere(Sphere::new(Vec3::new(-3.0, 1.0, 0.0), 0.9, glass.clone())),
+		);
+		// glass sphere with a cube carved out
+		scene.add_csg(
+			CsgOperation::Difference,
+			Object::Sphere(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 1.0, glass.clone())),
+			Object::Cube(Cube::new(
+				Vec3::new(0.5, 1.5, 0.5),
+				Vec3::uniform(0.6),
+				glass,
+			)),
+		);
+		// rounded cube
+		scene.add_csg(
+			CsgOperation::Intersection,
+			Object::Cube(Cube::new(
+				Vec3::new(3.0, 1.0, 0.0),
+				Vec3::uniform(0.8),
+				red.clone(),
+			)),
+			Object::Sphere(Sphere::new(Vec3::new(3.0, 1.0, 0.0), 1.05, red)),
+		);
+		// dumbbell
+		scene.add_csg(
+			CsgOperation::Union,
+			Object::Sphere(Sphere::new(Vec3::new(-1.5, 0.4, 2.5), 0.4, metal.clone())),
+			Object::Csg(Box::new(Csg::new(
+				CsgOperation::Union,
+				Object::Cube(Cube::new(
+					Vec3::new(0.0, 0.4, 2.5),
+					Vec3::new(1.5, 0.1, 0.1),
+					metal.clone(),
+				)),
+				Object::Sphere(Sphere::new(Vec3::new(1.5, 0.4, 2.5), 0.4, metal)),
+			))),
+		);
+		scene.add_sphere(Vec3::new(10000.0, 5000.0, 10000.0), 7500.0, sun_mat);
+
+		scene
+	}
+
+	// glass balls in front of bright stripes, meant for the spectral renderer
+	pub fn create_dispersion_scene() -> Self {
+		let mut scene = Scene::new(Vec::new());
+
+		let material_ground = Material::Lambertain {
+			albedo: Vec3::new(0.5, 0.5, 0.5),
+			emission: 0.0,
+		};
+		scene.add_cube(
+			Vec3::new(0.0, -1000.0, 0.0),
+			Vec3::uniform(1000.0),
+			material_ground,
+		);
+
+		scene.add_sphere(
+			Vec3::new(0.0, 1.0, -2.2),
+			1.0,
+			Material::DispersiveDielectric { ior: Ior::BK7 },
+		);
+		scene.add_sphere(
+			Vec3::new(0.0, 1.0, 0.0),
+			1.0,
+			Material::DispersiveDielectric {
+				ior: Ior::DENSE_FLINT,
+			},
+		);
+		scene.add_sphere(
+			Vec3::new(0.0, 1.0, 2.2),
+			1.0,
+			Material::DispersiveDielectric { ior: Ior::DIAMOND },
+		);
+
+		// black wall with white light stripes
+		let wall = Material::Lambertain {
+			albedo: Vec3::uniform(0.05),
+			emission: 0.0,
+		};
+		let stripe = Material::Lambertain {
+			albedo: Vec3::one(),
+			emission: 4.0,
+		};
+		scene.add_cube(Vec3::new(-6.0, 3.0, 0.0), Vec3::new(0.5, 3.0, 10.0), wall);
+		for i in -10..=10 {
+			scene.add_cube(
+				Vec3::new(-5.45, 3.0, i as Scalar),
+				Vec3::new(0.05, 3.0, 0.1),
+				stripe.clone(),
+			);
+		}
+
+		scene
+	}
+
+	// thin film and clearcoat materials
+	pub fn create_coating_scene() -> Self {
+		let mut scene = Scene::new(Vec::new());
+
+		let material_ground = Material::Lambertain {
+			albedo: Vec3::new(0.5, 0.5, 0.5),
+			emission: 0.0,
+		};
+		scene.add_cube(
+			Vec3::new(0.0, -1000.0, 0.0),
+			Vec3::uniform(1000.0),
+			material_ground,
+		);
+
+		// oil slick on a puddle of water over dark asphalt
+		scene.add_cube(
+			Vec3::new(2.0, 0.005, 0.0),
+			Vec3::new(1.5, 0.005, 4.0),
+			Material::Lambertain {
+				albedo: Vec3::uniform(0.05),
+				emission: 0.0,
+			},
+		);
+		scene.add_cube(
+			Vec3::new(2.0, 0.02, 0.0),
+			Vec3::new(1.5, 0.01, 4.0),
+			Material::Dielectric { ir: 1.33 }.with_thin_film(350.0, 1.45),
+		);
+
+		// soap bubble, the film is the only thing bending light
+		scene.add_sphere(
+			Vec3::new(0.0, 1.0, -2.2),
+			1.0,
+			Material::Dielectric { ir: 1.0 }.with_thin_film(500.0, 1.33),
+		);
+		// anodized metal
+		scene.add_sphere(
+			Vec3::new(0.0, 1.0, 0.0),
+			1.0,
+			Material::Metal {
+				albedo: Vec3::new(0.8, 0.8, 0.8),
+				fuzz: 0.05,
+			}
+			.with_thin_film(300.0, 2.0),
+		);
+		// car paint
+		scene.add_sphere(
+			Vec3::new(0.0, 1.0, 2.2),
+			1.0,
+			Material::Lambertain {
+				albedo: Vec3::new(0.7, 0.05, 0.05),
+				emission: 0.0,
+			}
+			.with_clearcoat(1.5, 0.0),
+		);
+
+		scene
+	}
+
+	// translucent materials lit from behind by a small bright light
+	pub fn create_subsurface_scene() -> Self {
+		let mut scene = Scene::new(Vec::new());
+
+		let material_ground = Material::Lambertain {
+			albedo: Vec3::new(0.5, 0.5, 0.5),
+			emission: 0.0,
+		};
+		scene.add_cube(
+			Vec3::new(0.0, -1000.0, 0.0),
+			Vec3::uniform(1000.0),
+			material_ground,
+		);
+
+		// marble
+		scene.add_cube(
+			Vec3::new(0.0, 0.8, -2.4),
+			Vec3::uniform(0.8),
+			Material::Subsurface {
+				albedo: Vec3::new(0.99, 0.99, 0.98),
+				mean_free_path: Vec3::new(0.3, 0.25, 0.2),
+				ir: 1.5,
+			},
+		);
+		// wax
+		scene.add_sphere(
+			Vec3::new(0.0, 1.0, 0.0),
+			1.0,
+			Material::Subsurface {
+				albedo: Vec3::new(0.99, 0.9, 0.6),
+				mean_free_path: Vec3::new(0.5, 0.3, 0.15),
+				ir: 1.45,
+			},
+		);
+		// milk
+		scene.add_sphere(
+			Vec3::new(0.0, 1.0, 2.4),
+			1.0,
+			Material::Subsurface {
+				albedo: Vec3::new(0.999, 0.999, 0.995),
+				mean_free_path: Vec3::new(0.05, 0.04, 0.03),
+				ir: 1.35,
+			},
+		);
+
+		let light = Material::Lambertain {
+			albedo: Vec3::one(),
+			emission: 30.0,
+		};
+		scene.add_sphere(Vec3::new(-4.0, 3.0, 0.0), 0.5, light);
+
+		scene
+	}
+
+	pub fn create_surface_maps_scene() -> Self {
+		let mut scene = Scene::new(Vec::new());
+
+		let material_ground = Material::Lambertain {
+			albedo: Vec3::new(0.5, 0.5, 0.5),
+			emission: 0.0,
+		};
+		scene.add_cube(
+			Vec3::new(0.0, -1000.0, 0.0),
+			Vec3::uniform(1000.0),
+			material_ground,
+		);
+
+		// height map of a brick wall, 8 rows with every other row shifted by half a brick
+		let bricks = Arc::new(ImageTexture::from_fn(256, 256, |u, v| {
+			let row = (v * 8.0).floor();
+			let x = (u * 4.0 + row * 0.5).fract();
+			let y = (v * 8.0).fract();
+			let mortar = x.min(1.0 - x) * 2.0 < 0.05 || y.min(1.0 - y) < 0.05;
+			Vec3::uniform(if mortar { 0.0 } else { 1.0 })
+		}));
+		// tangent space normal map of concentric ripples around the center of the texture
+		let ripples = Arc::new(ImageTexture::from_fn(512, 512, |u, v| {
+			let (x, y) = (u - 0.5, v - 0.5);
+			let radius = (x * x + y * y).sqrt().max(1e-6);
+			let slope = (radius * 40.0 * PI).cos() * 0.5;
+			// v points down in the image, the tangent space y axis up
+			let normal = Vec3::new(-slope * x / radius, slope * y / radius, 1.0).normalize();
+			normal * 0.5 + Vec3::uniform(0.5)
+		}));
+
+		// brick wall
+		scene.add_cube(
+			Vec3::new(0.0, 1.5, -2.5),
+			Vec3::new(2.5, 1.5, 0.1),
+			Material::Lambertain {
+				albedo: Vec3::new(0.6, 0.25, 0.15),
+				emission: 0.0,
+			}
+			.with_bump_map(bricks.clone(), 4.0),
+		);
+		// hammered metal
+		scene.add_sphere(
+			Vec3::new(-1.2, 0.8, 0.0),
+			0.8,
+			Material::Metal {
+				albedo: Vec3::new(0.8, 0.6, 0.3),
+				fuzz: 0.05,
+			}
+			.with_bump_map(bricks, 2.0),
+		);
+		// rippled ceramic
+		scene.add_sphere(
+			Vec3::new(1.2, 0.8, 0.0),
+			0.8,
+			Material::Lambertain {
+				albedo: Vec3::new(0.2, 0.4, 0.8),
+				emission: 0.0,
+			}
+			.with_clearcoat(1.5, 0.0)
+			.with_normal_map(ripples, 1.0),
+		);
+
+		let light = Material::Lambertain {
+			albedo: Vec3::one(),
+			emission: 30.0,
+		};
+		scene.add_sphere(Vec3::new(-3.0, 4.0, 3.0), 0.5, light);
+
+		scene
+	}
+
+	pub fn create_cutout_scene() -> Self {
+		let mut scene = Scene::new(Vec::new());
+
+		let material_ground = Material::Lambertain {
+			albedo: Vec3::new(0.5, 0.5, 0.5),
+			emission: 0.0,
+		};
+		scene.add_cube(
+			Vec3::new(0.0, -1000.0, 0.0),
+			Vec3::uniform(1000.0),
+			material_ground,
+		);
+
+		// wire fence, 12 x 8 holes
+		let fence = Arc::new(ImageTexture::from_fn(256, 256, |u, v| {
+			let x = (u * 12.0).fract();
+			let y = (v * 8.0).fract();
+			let wire = x.min(1.0 - x) < 0.08 || y.min(1.0 - y) < 0.08;
+			Vec3::uniform(if wire { 1.0 } else { 0.0 })
+		}));
+		// round holes all over a sphere
+		let dots = Arc::new(ImageTexture::from_fn(256, 128, |u, v| {
+			let x = (u * 16.0).fract() - 0.5;
+			let y = (v * 8.0).fract() - 0.5;
+			Vec3::uniform(if x * x + y * y < 0.1 { 0.0 } else { 1.0 })
+		}));
+
+		scene.add_cube(
+			Vec3::new(0.0, 1.5, -1.5),
+			Vec3::new(2.5, 1.5, 0.02),
+			Material::Metal {
+				albedo: Vec3::new(0.6, 0.6, 0.6),
+				fuzz: 0.3,
+			}
+			.with_opacity(ScalarTexture::Texture(fence)),
+		);
+		scene.add_sphere(
+			Vec3::new(-1.2, 0.8, 0.5),
+			0.8,
+			Material::Lambertain {
+				albedo: Vec3::new(0.2, 0.6, 0.2),
+				emission: 0.0,
+			}
+			.with_opacity(ScalarTexture::Texture(dots)),
+		);
+		// half of the rays pass through
+		scene.add_sphere(
+			Vec3::new(1.2, 0.8, 0.5),
+			0.8,
+			Material::Lambertain {
+				albedo: Vec3::new(0.8, 0.2, 0.2),
+				emission: 0.0,
+			}
+			.with_opacity(ScalarTexture::Constant(0.5)),
+		);
+
+		let light = Material::Lambertain {
+			albedo: Vec3::one(),
+			emission: 30.0,
+		};
+		scene.add_sphere(Vec3::new(2.0, 5.0, 4.0), 0.5, light);
+
+		scene
+	}
+
+	pub fn create_mix_scene() -> Self {
+		let mut scene = Scene::new(Vec::new());
+
+		let material_ground = Material::Lambertain {
+			albedo: Vec3::new(0.5, 0.5, 0.5),
+			emission: 0.0,
+		};
+		scene.add_cube(
+			Vec3::new(0.0, -1000.0, 0.0),
+			Vec3::uniform(1000.0),
+			material_ground,
+		);
+
+		// blotchy mask from a few overlapping waves, wraps around in u
+		let blotches = Arc::new(ImageTexture::from_fn(256, 128, |u, v| {
+			let (x, y) = (u * 2.0 * PI, v * PI);
+			let noise = (3.0 * x + 1.3).sin() * (4.0 * y).sin()
+				+ 0.6 * (7.0 * x + 5.0 * y).sin()
+				+ 0.4 * (11.0 * x - 9.0 * y + 2.0).sin();
+			Vec3::uniform(((noise - 0.2) * 2.0).clamp(0.0, 1.0))
+		}));
+		let stripes = Arc::new(ImageTexture::from_fn(64, 64, |_, v| {
+			Vec3::uniform(if (v * 8.0).fract() < 0.5 { 0.0 } else { 1.0 })
+		}));
+
+		// rusty metal
+		let rust = Material::Lambertain {
+			albedo: Vec3::new(0.45, 0.18, 0.06),
+			emission: 0.0,
+		}
+		.with_opacity(ScalarTexture::Texture(blotches));
+		scene.add_sphere(
+			Vec3::new(-2.0, 0.8, 0.0),
+			0.8,
+			Material::Metal {
+				albedo: Vec3::new(0.7, 0.7, 0.7),
+				fuzz: 0.1,
+			}
+			.with_coating(rust),
+		);
+		// dusty glass
+		let dust = Material::Lambertain {
+			albedo: Vec3::new(0.8, 0.75, 0.7),
+			emission: 0.0,
+		}
+		.with_opacity(ScalarTexture::Constant(0.3));
+		scene.add_sphere(
+			Vec3::new(0.0, 0.8, 0.0),
+			0.8,
+			Material::Dielectric { ir: 1.5 }.with_coating(dust),
+		);
+		// striped gold and porcelain
+		scene.add_sphere(
+			Vec3::new(2.0, 0.8, 0.0),
+			0.8,
+			Material::mix(
+				Material::Metal {
+					albedo: Vec3::new(0.9, 0.7, 0.3),
+					fuzz: 0.05,
+				},
+				Material::Lambertain {
+					albedo: Vec3::new(0.9, 0.9, 0.85),
+					emission: 0.0,
+				}
+				.with_clearcoat(1.5, 0.0),
+				ScalarTexture::Texture(stripes),
+			),
+		);
+
+		let light = Material::Lambertain {
+			albedo: Vec3::one(),
+			emission: 30.0,
+		};
+		scene.add_sphere(Vec3::new(-3.0, 4.0, 3.0), 0.5, light);
+
+		scene
+	}
 }
 
 impl Default for Scene {
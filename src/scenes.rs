@@ -225,9 +225,9 @@ pub fn create_sample_scene() -> (Vec<Sphere>, Vec<Cube>) {
 		albedo: Vec3::new(0.8, 0.4, 0.2),
 		emission: 15.0,
 	};
-	spheres.push(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 1.0, mat1));
+	spheres.push(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 1.0, mat1.clone()));
 	spheres.push(Sphere::new(Vec3::new(0.0, 1.0, 0.0), -0.98, mat1));
-	spheres.push(Sphere::new(Vec3::new(4.0, 1.0, 0.0), 1.0, mat2));
+	spheres.push(Sphere::new(Vec3::new(4.0, 1.0, 0.0), 1.0, mat2.clone()));
 	spheres.push(Sphere::new(Vec3::new(-4.0, 1.0, 0.0), 1.0, mat3));
 	spheres.push(Sphere::new(
 		Vec3::new(10000.0, 5000.0, 10000.0),
@@ -267,9 +267,13 @@ pub fn create_sample_scene() -> (Vec<Sphere>, Vec<Cube>) {
 						emission: random_mat,
 					};
 					if rand::rng().random_range(0.0..1.0) > 0.5 {
-						spheres.push(Sphere::new(center, 0.4, material));
+						spheres.push(Sphere::new(center, 0.4, material.clone()));
 					} else {
-						cubes.push(Cube::new(center, Vec3::new(0.4, 0.4, 0.4), material));
+						cubes.push(Cube::new(
+							center,
+							Vec3::new(0.4, 0.4, 0.4),
+							material.clone(),
+						));
 					}
 				} else if random_mat < 0.85 {
 					// metal
@@ -277,18 +281,26 @@ pub fn create_sample_scene() -> (Vec<Sphere>, Vec<Cube>) {
 					let fuzz = rand::rng().random_range(0.0..0.3);
 					let material = Material::Metal { fuzz, albedo };
 					if rand::rng().random_range(0.0..1.0) > 0.5 {
-						spheres.push(Sphere::new(center, 0.4, material));
+						spheres.push(Sphere::new(center, 0.4, material.clone()));
 					} else {
-						cubes.push(Cube::new(center, Vec3::new(0.4, 0.4, 0.4), material));
+						cubes.push(Cube::new(
+							center,
+							Vec3::new(0.4, 0.4, 0.4),
+							material.clone(),
+						));
 					}
 				} else {
 					// glass
 					let material = Material::Dielectric { ir: 1.5 };
 					if rand::rng().random_range(0.0..1.0) > 0.5 {
-						spheres.push(Sphere::new(center, 0.4, material));
+						spheres.push(Sphere::new(center, 0.4, material.clone()));
 						spheres.push(Sphere::new(center, -0.38, material));
 					} else {
-						cubes.push(Cube::new(center, Vec3::new(0.4, 0.4, 0.4), material));
+						cubes.push(Cube::new(
+							center,
+							Vec3::new(0.4, 0.4, 0.4),
+							material.clone(),
+						));
 						cubes.push(Cube::new(center, Vec3::new(-0.38, -0.38, -0.38), material));
 					}
 				}
@@ -0,0 +1,754 @@
+use crate::{
+	AdaptiveSampling, AovBuffer, AovPixel, BVH, Checkpoint, DenoiseSettings, Film, FilmPixel,
+	Filter, FilterSampler, OutlierRejection, Projection, RenderSettings, SamplerKind, Scalar, Tile,
+	TileOrder, Vec3, checkpoint::debug_hash, denoise, render, render_with_aovs,
+};
+use rayon::prelude::*;
+use std::{
+	ops::Range,
+	sync::{
+		Arc, Mutex, OnceLock,
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+	},
+};
+
+// Shared flag to stop a render from another thread (or a progress callback), the current pass
+// finishes the rows it already started
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+
+	fn clear(&self) {
+		self.0.store(false, Ordering::Relaxed);
+	}
+}
+
+// Passed to the progress callback after every pass
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderProgress {
+	// passes since the last reset, every pass adds one sample to each unfinished pixel
+	pub pass: u32,
+	// pixels that got a sample in this pass, the others are finished
+	pub sampled_pixels: usize,
+	pub pixel_count: usize,
+	pub max_samples: u32,
+}
+
+type ProgressCallback = Box<dyn Fn(&RenderProgress) + Send + Sync>;
+type TileCallback = Box<dyn Fn(&Tile, &Film) + Send + Sync>;
+
+// the rows of one tile in the film (and the aov buffer), tiles don't overlap so all tiles of a step
+// are rendered into the film at the same time
+struct TileView<'a> {
+	tile: &'a Tile,
+	rows: Vec<&'a mut [FilmPixel]>,
+	aov_rows: Option<Vec<&'a mut [AovPixel]>>,
+	sampled_pixels: usize,
+}
+
+impl<'a> TileView<'a> {
+	fn new(
+		tile: &'a Tile,
+		rows: Vec<&'a mut [FilmPixel]>,
+		aov_rows: Option<Vec<&'a mut [AovPixel]>>,
+	) -> Mutex<Self> {
+		Mutex::new(Self {
+			tile,
+			rows,
+			aov_rows,
+			sampled_pixels: 0,
+		})
+	}
+}
+
+// splits the pixels of an image into the rows of every tile, the tiles have to be on the grid of
+// tile_size (as created by TileOrder::create_tiles)
+fn tile_rows<'a, T>(
+	pixels: &'a mut [T],
+	width: usize,
+	tile_size: usize,
+	tiles: &[Tile],
+) -> Vec<Vec<&'a mut [T]>> {
+	let columns = width.div_ceil(tile_size);
+	let grid_rows = (pixels.len() / width).div_ceil(tile_size);
+	let mut tile_of_cell = vec![None; columns * grid_rows];
+	for (i, tile) in tiles.iter().enumerate() {
+		tile_of_cell[tile.y / tile_size * columns + tile.x / tile_size] = Some(i);
+	}
+	let mut rows: Vec<Vec<&mut [T]>> = tiles
+		.iter()
+		.map(|tile| Vec::with_capacity(tile.height))
+		.collect();
+	for (y, row) in pixels.chunks_mut(width).enumerate() {
+		for (column, part) in row.chunks_mut(tile_size).enumerate() {
+			if let Some(i) = tile_of_cell[y / tile_size * columns + column] {
+				rows[i].push(part);
+			}
+		}
+	}
+	rows
+}
+
+// Progressive render of one scene through one camera: owns the bvh, the camera, the settings and
+// the film the samples are accumulated on. Frontends call step (one sample per pixel, for
+// interactive previews) or render (until every pixel is finished) and resolve the film.
+// Every pass is split into tiles that are handed to the render threads in the tile order.
+pub struct RenderSession {
+	bvh: BVH,
+	camera: Box<dyn Projection>,
+	settings: RenderSettings,
+	sampling: AdaptiveSampling,
+	sampler_kind: SamplerKind,
+	film: Film,
+	filter_sampler: FilterSampler,
+	// None until aovs are enabled
+	aovs: Option<AovBuffer>,
+	collect_aovs: bool,
+	tile_size: usize,
+	tile_order: TileOrder,
+	tiles: Vec<Tile>,
+	// first tile of the current pass that isn't rendered yet
+	next_tile: usize,
+	pass_sampled_pixels: usize,
+	pass: u32,
+	cancel_token: CancelToken,
+	progress_callback: Option<ProgressCallback>,
+	tile_callback: Option<TileCallback>,
+	// hashes of the debug output of the bvh and the camera for checkpoints, computed once they are
+	// needed and reset when they change
+	scene_hash: OnceLock<u64>,
+	camera_hash: OnceLock<u64>,
+}
+
+impl RenderSession {
+	pub const DEFAULT_TILE_SIZE: usize = 32;
+	pub const DEFAULT_SAMPLES: u32 = 1024;
+
+	// DEFAULT_SAMPLES samples per pixel with the sobol sampler, a box filter and spiral tiles until
+	// configured otherwise, interactive frontends that keep accumulating while the view doesn't
+	// change use AdaptiveSampling::fixed(u32::MAX) (render() never finishes then)
+	pub fn new(
+		bvh: BVH,
+		camera: Box<dyn Projection>,
+		settings: RenderSettings,
+		width: usize,
+		height: usize,
+	) -> Self {
+		let film = Film::new(width, height, Filter::default(), None);
+		Self {
+			bvh,
+			camera,
+			settings,
+			sampling: AdaptiveSampling::fixed(Self::DEFAULT_SAMPLES),
+			sampler_kind: SamplerKind::Sobol,
+			filter_sampler: FilterSampler::new(film.filter),
+			film,
+			aovs: None,
+			collect_aovs: false,
+			tile_size: Self::DEFAULT_TILE_SIZE,
+			tile_order: TileOrder::default(),
+			tiles: TileOrder::default().create_tiles(width, height, Self::DEFAULT_TILE_SIZE),
+			next_tile: 0,
+			pass_sampled_pixels: 0,
+			pass: 0,
+			cancel_token: CancelToken::new(),
+			progress_callback: None,
+			tile_callback: None,
+			scene_hash: OnceLock::new(),
+			camera_hash: OnceLock::new(),
+		}
+	}
+
+	pub fn with_sampling(mut self, sampling: AdaptiveSampling) -> Self {
+		self.sampling = sampling;
+		self
+	}
+
+	pub fn with_sampler(mut self, sampler_kind: SamplerKind) -> Self {
+		self.sampler_kind = sampler_kind;
+		self
+	}
+
+	pub fn with_filter(mut self, filter: Filter) -> Self {
+		self.film.filter = filter;
+		self.filter_sampler = FilterSampler::new(filter);
+		self.clear();
+		self
+	}
+
+	// clamps samples far above the mean of their pixel as they are added to the film
+	pub fn with_outlier_rejection(mut self, outlier_rejection: OutlierRejection) -> Self {
+		self.film.outlier_rejection = Some(outlier_rejection);
+		self.clear();
+		self
+	}
+
+	// collects the aovs of every sample, e.g. as feature buffers for the denoiser
+	pub fn with_aovs(mut self) -> Self {
+		self.aovs = Some(AovBuffer::new(self.film.width, self.film.height));
+		self.collect_aovs = true;
+		self
+	}
+
+	pub fn with_progress(
+		mut self,
+		callback: impl Fn(&RenderProgress) + Send + Sync + 'static,
+	) -> Self {
+		self.progress_callback = Some(Box::new(callback));
+		self
+	}
+
+	pub fn with_tiles(mut self, tile_size: usize, tile_order: TileOrder) -> Self {
+		self.tile_size = tile_size;
+		self.tile_order = tile_order;
+		self.tiles = tile_order.create_tiles(self.film.width, self.film.height, tile_size);
+		self.clear();
+		self
+	}
+
+	// called for every tile that got samples once they are in the film, in render order after the
+	// tiles of a step are done
+	pub fn with_tile_callback(
+		mut self,
+		callback: impl Fn(&Tile, &Film) + Send + Sync + 'static,
+	) -> Self {
+		self.tile_callback = Some(Box::new(callback));
+		self
+	}
+
+	pub fn with_cancel_token(mut self, cancel_token: CancelToken) -> Self {
+		self.cancel_token = cancel_token;
+		self
+	}
+
+	// discards all samples and allows rendering again after a cancel
+	pub fn reset(&mut self) {
+		self.clear();
+		self.cancel_token.clear();
+	}
+
+	// discards all samples, e.g. after the view changed. A cancel stays in effect, it could have
+	// come from another thread while the view changed.
+	fn clear(&mut self) {
+		self.film.clear();
+		if let Some(aovs) = &mut self.aovs {
+			*aovs = AovBuffer::new(aovs.width, aovs.height);
+		}
+		self.next_tile = 0;
+		self.pass_sampled_pixels = 0;
+		self.pass = 0;
+	}
+
+	pub fn set_camera(&mut self, camera: Box<dyn Projection>) {
+		self.camera = camera;
+		self.camera_hash = OnceLock::new();
+		self.clear();
+	}
+
+	pub fn set_bvh(&mut self, bvh: BVH) {
+		self.bvh = bvh;
+		self.scene_hash = OnceLock::new();
+		self.clear();
+	}
+
+	pub fn set_settings(&mut self, settings: RenderSettings) {
+		self.settings = settings;
+		self.clear();
+	}
+
+	// the camera has to be created for the new size as well
+	pub fn resize(&mut self, width: usize, height: usize, camera: Box<dyn Projection>) {
+		self.film = Film::new(width, height, self.film.filter, self.film.outlier_rejection);
+		self.tiles = self.tile_order.create_tiles(width, height, self.tile_size);
+		if self.aovs.is_some() {
+			self.aovs = Some(AovBuffer::new(width, height));
+		}
+		self.set_camera(camera);
+	}
+
+	// aovs are only collected while enabled, the buffer keeps what was collected so far
+	pub fn set_collect_aovs(&mut self, collect_aovs: bool) {
+		self.collect_aovs = collect_aovs && self.aovs.is_some();
+	}
+
+	// adds one sample to every pixel that isn't finished yet (the rest of the pass if it was started
+	// with step_tiles), returns how many pixels were sampled in the whole pass
+	pub fn step(&mut self) -> usize {
+		if self.cancel_token.is_cancelled() {
+			return 0;
+		}
+		let sampled_pixels = self.pass_sampled_pixels;
+		sampled_pixels + self.step_tiles(self.tiles.len() - self.next_tile)
+	}
+
+	// renders the next count tiles of the current pass in parallel, returns how many pixels were
+	// sampled. The pass is complete once its last tile is rendered.
+	pub fn step_tiles(&mut self, count: usize) -> usize {
+		if self.cancel_token.is_cancelled() || self.tiles.is_empty() {
+			return 0;
+		}
+		let end = (self.next_tile + count).min(self.tiles.len());
+		let tiles = &self.tiles[self.next_tile..end];
+		let width = self.film.width;
+		let tile_size = self.tile_size.max(1);
+		let film_rows = tile_rows(&mut self.film.pixels, width, tile_size, tiles);
+		let collect_aovs = self.collect_aovs;
+		let aov_rows = self
+			.aovs
+			.as_mut()
+			.filter(|_| collect_aovs)
+			.map(|aovs| tile_rows(&mut aovs.pixels, width, tile_size, tiles));
+		// every view is only locked by the worker that renders its tile
+		let views: Vec<Mutex<TileView>> = match aov_rows {
+			Some(aov_rows) => tiles
+				.iter()
+				.zip(film_rows)
+				.zip(aov_rows)
+				.map(|((tile, rows), aov_rows)| TileView::new(tile, rows, Some(aov_rows)))
+				.collect(),
+			None => tiles
+				.iter()
+				.zip(film_rows)
+				.map(|(tile, rows)| TileView::new(tile, rows, None))
+				.collect(),
+		};
+		let outlier_rejection = self.film.outlier_rejection.as_ref();
+		let camera = self.camera.as_ref();
+		let bvh = &self.bvh;
+		let settings = &self.settings;
+		let sampling = &self.sampling;
+		let sampler_kind = self.sampler_kind;
+		let filter_sampler = &self.filter_sampler;
+		let cancel_token = &self.cancel_token;
+		// every worker takes the next tile in order, rayon's splitting of a parallel iterator
+		// would ignore the tile order
+		let next_tile = AtomicUsize::new(0);
+		(0..rayon::current_num_threads())
+			.into_par_iter()
+			.for_each(|_| {
+				let mut sampler = sampler_kind.create();
+				loop {
+					let index = next_tile.fetch_add(1, Ordering::Relaxed);
+					if index >= views.len() || cancel_token.is_cancelled() {
+						break;
+					}
+					let mut view = views[index].lock().unwrap();
+					let TileView {
+						tile,
+						rows,
+						aov_rows,
+						sampled_pixels,
+					} = &mut *view;
+					for (x, y) in tile.pixels() {
+						let pixel = &mut rows[y - tile.y][x - tile.x];
+						if sampling.is_finished(pixel.variance()) {
+							continue;
+						}
+						sampler.start_pixel_sample(x, y, pixel.sample_count());
+						let (offset_x, offset_y, weight) = filter_sampler.sample(sampler.get_2d());
+						let film_x = x as Scalar + 0.5 + offset_x;
+						let film_y = fold_into(y as Scalar + 0.5 + offset_y, camera.image_rows(y));
+						let color = match aov_rows {
+							Some(aov_rows) => {
+								let (color, aovs) = render_with_aovs(
+									film_x,
+									film_y,
+									camera,
+									bvh,
+									settings,
+									sampler.as_mut(),
+								);
+								aov_rows[y - tile.y][x - tile.x].add_sample(&aovs);
+								color
+							}
+							None => render(film_x, film_y, camera, bvh, settings, sampler.as_mut()),
+						};
+						pixel.add_sample(color, weight, outlier_rejection);
+						*sampled_pixels += 1;
+					}
+				}
+			});
+
+		// the views are done with the film, the callback sees the finished tiles in it
+		let rendered_tiles: Vec<(Tile, usize)> = views
+			.into_iter()
+			.map(|view| {
+				let view = view.into_inner().unwrap();
+				(*view.tile, view.sampled_pixels)
+			})
+			.collect();
+		let mut sampled_pixels = 0;
+		for (tile, tile_sampled_pixels) in rendered_tiles {
+			if let Some(callback) = &self.tile_callback
+				&& tile_sampled_pixels > 0
+			{
+				callback(&tile, &self.film);
+			}
+			sampled_pixels += tile_sampled_pixels;
+		}
+		// a cancelled pass isn't continued, its tiles are rendered again after a reset anyway
+		if self.cancel_token.is_cancelled() {
+			return sampled_pixels;
+		}
+		self.next_tile = end;
+		self.pass_sampled_pixels += sampled_pixels;
+		if self.next_tile == self.tiles.len() {
+			if self.pass_sampled_pixels > 0 {
+				self.pass += 1;
+				if let Some(callback) = &self.progress_callback {
+					callback(&RenderProgress {
+						pass: self.pass,
+						sampled_pixels: self.pass_sampled_pixels,
+						pixel_count: self.film.pixels.len(),
+						max_samples: self.sampling.max_samples,
+					});
+				}
+			}
+			self.next_tile = 0;
+			self.pass_sampled_pixels = 0;
+		}
+		sampled_pixels
+	}
+
+	// snapshot of the film and the aovs, mid pass the tiles rendered so far just have one sample more
+	pub fn checkpoint(&self) -> Checkpoint {
+		let (scene_hash, camera_hash, settings_hash) = self.hashes();
+		Checkpoint {
+			width: self.film.width,
+			height: self.film.height,
+			pass: self.pass,
+			scene_hash,
+			camera_hash,
+			settings_hash,
+			pixels: self.film.pixels.clone(),
+			aovs: self.aovs.as_ref().map(|aovs| aovs.pixels.clone()),
+		}
+	}
+
+	// continues from the checkpoint, it has to be of the same scene, camera, settings and filter.
+	// The sampling can differ, e.g. to add more samples to a finished render.
+	pub fn restore(&mut self, checkpoint: Checkpoint) -> Result<(), String> {
+		if (checkpoint.width, checkpoint.height) != (self.film.width, self.film.height) {
+			return Err(format!(
+				"checkpoint is {}x{}, expected {}x{}",
+				checkpoint.width, checkpoint.height, self.film.width, self.film.height
+			));
+		}
+		let (scene_hash, camera_hash, settings_hash) = self.hashes();
+		if checkpoint.scene_hash != scene_hash {
+			return Err("checkpoint was rendered with a different scene".to_string());
+		}
+		if checkpoint.camera_hash != camera_hash {
+			return Err("checkpoint was rendered with a different camera".to_string());
+		}
+		if checkpoint.settings_hash != settings_hash {
+			return Err("checkpoint was rendered with different settings".to_string());
+		}
+		self.clear();
+		self.film.pixels = checkpoint.pixels;
+		// without aovs in the checkpoint they are only averaged over the new samples
+		if let Some(aovs) = &mut self.aovs
+			&& let Some(pixels) = checkpoint.aovs
+		{
+			aovs.pixels = pixels;
+		}
+		self.pass = checkpoint.pass;
+		Ok(())
+	}
+
+	// of everything samples from different runs can't be combined with, the settings are small
+	// enough to hash every time
+	fn hashes(&self) -> (u64, u64, u64) {
+		(
+			*self.scene_hash.get_or_init(|| debug_hash(&self.bvh)),
+			*self.camera_hash.get_or_init(|| debug_hash(&self.camera)),
+			debug_hash(&(
+				&self.settings,
+				&self.film.filter,
+				&self.film.outlier_rejection,
+				&self.sampler_kind,
+			)),
+		)
+	}
+
+	// steps until every pixel is finished, false if it was cancelled before
+	pub fn render(&mut self) -> bool {
+		while self.step() > 0 {}
+		!self.cancel_token.is_cancelled()
+	}
+
+	pub fn cancel_token(&self) -> &CancelToken {
+		&self.cancel_token
+	}
+
+	pub fn pass(&self) -> u32 {
+		self.pass
+	}
+
+	// in render order
+	pub fn tiles(&self) -> &[Tile] {
+		&self.tiles
+	}
+
+	pub fn camera(&self) -> &dyn Projection {
+		self.camera.as_ref()
+	}
+
+	pub fn bvh(&self) -> &BVH {
+		&self.bvh
+	}
+
+	pub fn settings(&self) -> &RenderSettings {
+		&self.settings
+	}
+
+	pub fn film(&self) -> &Film {
+		&self.film
+	}
+
+	pub fn aovs(&self) -> Option<&AovBuffer> {
+		self.aovs.as_ref()
+	}
+
+	// linear color space
+	pub fn resolve(&self) -> Vec<Vec3> {
+		self.film.resolve()
+	}
+
+	pub fn resolve_to_image(&self) -> image::RgbImage {
+		self.film.resolve_to_image()
+	}
+
+	// gamma corrected colors, filtered with the aovs as feature buffers when they are collected
+	pub fn resolve_denoised(&self, denoise_settings: &DenoiseSettings) -> Vec<Vec3> {
+		let output: Vec<Vec3> = self
+			.resolve()
+			.iter()
+			.map(|color| color.linear_to_gamma())
+			.collect();
+		match &self.aovs {
+			Some(aovs) => denoise(&output, aovs, denoise_settings),
+			None => output,
+		}
+	}
+}
+
+// mirrors a sample position that the filter moved past the edge of its pixel's image back into
+// it, so e.g. the samples of the last rows of a stereo eye aren't traced by the other eye
+#[inline(always)]
+fn fold_into(position: Scalar, range: Range<Scalar>) -> Scalar {
+	if position < range.start {
+		2.0 * range.start - position
+	} else if position >= range.end {
+		(2.0 * range.end - position).min(range.end.next_down())
+	} else {
+		position
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// a diffuse sphere in front of the sky, cheap enough for a thousand passes
+	fn test_session() -> RenderSession {
+		let mut scene = crate::Scene::new(Vec::new());
+		scene.add_sphere(
+			Vec3::zero(),
+			1.0,
+			crate::Material::Lambertain {
+				albedo: Vec3::uniform(0.5),
+				emission: 0.0,
+			},
+		);
+		let camera = crate::Camera::new(
+			Vec3::new(0.0, 0.0, 3.0),
+			Vec3::new(0.0, 0.0, -1.0),
+			40.0,
+			3.0,
+			0.0,
+			4,
+			3,
+		);
+		RenderSession::new(
+			BVH::new(scene).unwrap(),
+			Box::new(camera),
+			RenderSettings::default(),
+			4,
+			3,
+		)
+	}
+
+	#[test]
+	fn render_finishes_with_the_default_sampling() {
+		let mut session = test_session();
+		assert!(session.render());
+		assert_eq!(session.pass(), RenderSession::DEFAULT_SAMPLES);
+	}
+
+	#[test]
+	fn only_reset_clears_a_cancel() {
+		let mut session = test_session();
+		session.cancel_token().clone().cancel();
+		session.set_settings(RenderSettings::default());
+		assert_eq!(session.step(), 0);
+		assert!(!session.render());
+
+		session.reset();
+		assert_eq!(session.step(), 4 * 3);
+	}
+
+	#[test]
+	fn restore_continues_a_checkpoint() {
+		let mut session = test_session().with_sampling(AdaptiveSampling::fixed(2));
+		session.render();
+		let checkpoint = session.checkpoint();
+
+		let mut resumed = test_session().with_sampling(AdaptiveSampling::fixed(4));
+		resumed.restore(checkpoint).unwrap();
+		assert_eq!(resumed.pass(), 2);
+		assert_eq!(resumed.resolve(), session.resolve());
+		resumed.render();
+		assert_eq!(resumed.pass(), 4);
+
+		let mut uninterrupted = test_session().with_sampling(AdaptiveSampling::fixed(4));
+		uninterrupted.render();
+		assert_eq!(resumed.resolve(), uninterrupted.resolve());
+	}
+
+	#[test]
+	fn restore_rejects_other_sessions() {
+		let checkpoint = test_session().checkpoint();
+		let mut other_sampler = test_session().with_sampler(SamplerKind::Halton);
+		assert!(other_sampler.restore(checkpoint.clone()).is_err());
+		let mut other_filter = test_session().with_filter(Filter::from_name("tent").unwrap());
+		assert!(other_filter.restore(checkpoint.clone()).is_err());
+
+		let mut session = test_session();
+		session.set_camera(Box::new(crate::Camera::new(
+			Vec3::new(0.0, 0.0, 4.0),
+			Vec3::new(0.0, 0.0, -1.0),
+			40.0,
+			3.0,
+			0.0,
+			4,
+			3,
+		)));
+		assert!(session.restore(checkpoint.clone()).is_err());
+		session.set_camera(test_session().camera);
+		assert!(session.restore(checkpoint).is_ok());
+	}
+
+	#[test]
+	fn scene_hash_sees_texture_content() {
+		let textured = |value: Scalar| {
+			let mut scene = crate::Scene::new(Vec::new());
+			scene.add_sphere(
+				Vec3::zero(),
+				1.0,
+				crate::Material::Lambertain {
+					albedo: Vec3::uniform(0.5),
+					emission: 0.0,
+				}
+				.with_normal_map(
+					Arc::new(crate::ImageTexture::from_fn(2, 2, |u, _| {
+						Vec3::new(u, value, 1.0)
+					})),
+					1.0,
+				),
+			);
+			let mut session = test_session();
+			session.set_bvh(BVH::new(scene).unwrap());
+			session
+		};
+		let checkpoint = textured(0.5).checkpoint();
+		assert!(textured(0.5).restore(checkpoint.clone()).is_ok());
+		assert!(textured(0.6).restore(checkpoint).is_err());
+	}
+
+	#[test]
+	fn tile_rows_are_the_pixels_of_the_tiles() {
+		let (width, height) = (11, 7);
+		let mut pixels: Vec<usize> = (0..width * height).collect();
+		let tiles = TileOrder::Hilbert.create_tiles(width, height, 4);
+		// every other tile, like a step in the middle of a pass
+		let tiles: Vec<Tile> = tiles.into_iter().skip(1).step_by(2).collect();
+		let rows = tile_rows(&mut pixels, width, 4, &tiles);
+		for (tile, rows) in tiles.iter().zip(rows) {
+			assert_eq!(rows.len(), tile.height);
+			for (x, y) in tile.pixels() {
+				assert_eq!(rows[y - tile.y][x - tile.x], y * width + x);
+			}
+		}
+	}
+
+	#[test]
+	fn tiles_are_written_to_the_film() {
+		let tile_count = std::sync::Arc::new(AtomicUsize::new(0));
+		let mut session = test_session()
+			.with_tiles(2, TileOrder::Spiral)
+			.with_tile_callback({
+				let tile_count = tile_count.clone();
+				move |tile, film| {
+					for (x, y) in tile.pixels() {
+						assert_eq!(film.pixels[y * film.width + x].sample_count(), 1);
+					}
+					tile_count.fetch_add(1, Ordering::Relaxed);
+				}
+			});
+		assert_eq!(session.step_tiles(3), 2 * 2 + 2 * 2 + 2);
+		assert_eq!(session.step(), 4 * 3);
+		assert_eq!(tile_count.load(Ordering::Relaxed), session.tiles().len());
+		assert!(
+			session
+				.film()
+				.pixels
+				.iter()
+				.all(|pixel| pixel.sample_count() == 1)
+		);
+	}
+
+	#[test]
+	fn fold_into_mirrors_positions_back_into_the_image() {
+		assert_eq!(fold_into(3.0, 0.0..8.0), 3.0);
+		assert_eq!(fold_into(9.0, 0.0..8.0), 7.0);
+		assert_eq!(fold_into(-0.5, 0.0..8.0), 0.5);
+		assert!(fold_into(8.0, 0.0..8.0) < 8.0);
+		let unbounded = Scalar::NEG_INFINITY..Scalar::INFINITY;
+		assert_eq!(fold_into(-2.0, unbounded.clone()), -2.0);
+		assert_eq!(fold_into(1e9, unbounded), 1e9);
+	}
+
+	#[test]
+	fn stereo_eyes_keep_their_samples() {
+		let eye_height = 4;
+		let camera = crate::StereoCamera::new(
+			Vec3::zero(),
+			Vec3::new(0.0, 0.0, -1.0),
+			40.0,
+			0.064,
+			10.0,
+			8,
+			eye_height,
+		);
+		// the last row of the left eye and the first one of the right eye, with a 2 pixel filter
+		for (y, left) in [(eye_height - 1, true), (eye_height, false)] {
+			for offset in [-2.0, -1.0, 0.0, 1.0, 2.0] {
+				let film_y = fold_into(y as Scalar + 0.5 + offset, camera.image_rows(y));
+				assert_eq!(film_y < eye_height as Scalar, left, "{y} {offset} {film_y}");
+			}
+		}
+	}
+}
@@ -0,0 +1,260 @@
+use crate::{
+	AABB, Bounded, Crossing, Hit, Hittable, Material, Object, Ray, Renderable, Scalar, Solid, Span,
+	Vec3,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsgOperation {
+	Union,
+	Intersection,
+	// left with right carved out
+	Difference,
+}
+
+impl CsgOperation {
+	#[inline(always)]
+	fn is_inside(&self, inside_left: bool, inside_right: bool) -> bool {
+		match self {
+			Self::Union => inside_left || inside_right,
+			Self::Intersection => inside_left && inside_right,
+			Self::Difference => inside_left && !inside_right,
+		}
+	}
+}
+
+// Constructive solid geometry node, every surface keeps the material of the child it belongs to
+#[derive(Clone, Debug)]
+pub struct Csg {
+	pub operation: CsgOperation,
+	pub left: Object,
+	pub right: Object,
+	aabb: AABB,
+}
+
+impl Csg {
+	pub fn new(operation: CsgOperation, left: Object, right: Object) -> Self {
+		let aabb = match operation {
+			CsgOperation::Union => AABB::surrounding(left.get_aabb(), right.get_aabb()),
+			CsgOperation::Intersection => AABB::overlap(left.get_aabb(), right.get_aabb()),
+			CsgOperation::Difference => left.get_aabb(),
+		};
+		Csg {
+			operation,
+			left,
+			right,
+			aabb,
+		}
+	}
+
+	// the crossing of the first surface in front of the ray origin
+	#[inline(always)]
+	fn first_crossing(&self, ray: &Ray) -> Option<Crossing<'_>> {
+		self.get_spans(ray)
+			.iter()
+			.flat_map(|span| [span.enter, span.exit])
+			.find(|crossing| crossing.distance > 0.001)
+	}
+}
+
+impl Hittable for Csg {
+	// the spans are only evaluated here, the surface of the hit is carried along in the crossing
+	#[inline(always)]
+	fn hit(&self, ray: &Ray) -> Option<Hit<'_>> {
+		self.first_crossing(ray).map(|crossing| Hit {
+			distance: crossing.distance,
+			crossing: Some(crossing),
+		})
+	}
+}
+
+impl Bounded for Csg {
+	#[inline(always)]
+	fn get_aabb(&self) -> AABB {
+		self.aabb
+	}
+}
+
+// only used if the crossing of the hit isn't available, ray has to be the ray that hit p
+impl Renderable for Csg {
+	#[inline(always)]
+	fn get_normal(&self, _p: &Vec3, ray: &Ray) -> Vec3 {
+		self.first_crossing(ray)
+			.map(|crossing| crossing.normal)
+			.unwrap_or(-ray.dir)
+	}
+
+	#[inline(always)]
+	fn get_material(&self, _p: &Vec3, ray: &Ray) -> Option<&Material> {
+		self.first_crossing(ray).map(|crossing| crossing.material)
+	}
+
+	// uses the frame of the child whose surface p is on
+	#[inline(always)]
+	fn get_surface_frame(&self, p: &Vec3, ray: &Ray) -> ((Scalar, Scalar), Vec3) {
+		self.first_crossing(ray)
+			.map(|crossing| crossing.get_surface_frame(p, ray))
+			.unwrap_or(((0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)))
+	}
+}
+
+impl Solid for Csg {
+	fn get_spans(&self, ray: &Ray) -> Vec<Span<'_>> {
+		let left = self.left.get_spans(ray);
+		let right = self.right.get_spans(ray);
+
+		// (crossing, is from right child, is entering the child)
+		let mut events: Vec<(Crossing, bool, bool)> =
+			Vec::with_capacity(2 * (left.len() + right.len()));
+		for span in left.iter() {
+			events.push((span.enter, false, true));
+			events.push((span.exit, false, false));
+		}
+		for span in right.iter() {
+			events.push((span.enter, true, true));
+			events.push((span.exit, true, false));
+		}
+		events.sort_unstable_by(|a, b| a.0.distance.total_cmp(&b.0.distance));
+
+		let mut spans = Vec::new();
+		let mut inside_left = false;
+		let mut inside_right = false;
+		let mut enter: Option<Crossing> = None;
+		for (mut crossing, from_right, entering) in events {
+			let was_inside = self.operation.is_inside(inside_left, inside_right);
+			if from_right {
+				inside_right = entering;
+			} else {
+				inside_left = entering;
+			}
+			let is_inside = self.operation.is_inside(inside_left, inside_right);
+			if was_inside == is_inside {
+				continue;
+			}
+
+			// the carved out surface faces into the right child
+			if from_right && self.operation == CsgOperation::Difference {
+				crossing.normal = -crossing.normal;
+			}
+			if is_inside {
+				enter = Some(crossing);
+			} else if let Some(enter) = enter.take() {
+				spans.push(Span {
+					enter,
+					exit: crossing,
+				});
+			}
+		}
+		spans
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Sphere;
+
+	fn sphere(x: Scalar, radius: Scalar) -> Object {
+		let material = Material::Lambertain {
+			albedo: Vec3::one(),
+			emission: 0.0,
+		};
+		Object::Sphere(Sphere::new(Vec3::new(x, 0.0, 0.0), radius, material))
+	}
+
+	// two unit spheres along the x axis overlapping between 0 and 1
+	fn csg(operation: CsgOperation) -> Csg {
+		Csg::new(operation, sphere(0.0, 1.0), sphere(1.0, 1.0))
+	}
+
+	fn distances(csg: &Csg, ray: &Ray) -> Vec<(Scalar, Scalar)> {
+		csg.get_spans(ray)
+			.iter()
+			.map(|span| (span.enter.distance, span.exit.distance))
+			.collect()
+	}
+
+	fn assert_spans(actual: Vec<(Scalar, Scalar)>, expected: &[(Scalar, Scalar)]) {
+		assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+		for (actual, expected) in actual.iter().zip(expected) {
+			assert!(
+				(actual.0 - expected.0).abs() < 1e-9,
+				"{actual:?} != {expected:?}"
+			);
+			assert!(
+				(actual.1 - expected.1).abs() < 1e-9,
+				"{actual:?} != {expected:?}"
+			);
+		}
+	}
+
+	fn along_x(origin: Scalar) -> Ray {
+		Ray::new(Vec3::new(origin, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))
+	}
+
+	#[test]
+	fn union_merges_overlapping_spans() {
+		assert_spans(
+			distances(&csg(CsgOperation::Union), &along_x(-5.0)),
+			&[(4.0, 7.0)],
+		);
+	}
+
+	#[test]
+	fn intersection_keeps_the_overlap() {
+		assert_spans(
+			distances(&csg(CsgOperation::Intersection), &along_x(-5.0)),
+			&[(5.0, 6.0)],
+		);
+	}
+
+	#[test]
+	fn difference_carves_out_the_right_child() {
+		let difference = csg(CsgOperation::Difference);
+		let ray = along_x(-5.0);
+		assert_spans(distances(&difference, &ray), &[(4.0, 5.0)]);
+		// the carved surface faces into the right child
+		let exit = difference.get_spans(&ray)[0].exit;
+		assert!((exit.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+	}
+
+	#[test]
+	fn disjoint_spans_stay_separate() {
+		let union = Csg::new(CsgOperation::Union, sphere(0.0, 1.0), sphere(4.0, 1.0));
+		assert_spans(
+			distances(&union, &along_x(-5.0)),
+			&[(4.0, 6.0), (8.0, 10.0)],
+		);
+		let intersection = Csg::new(
+			CsgOperation::Intersection,
+			sphere(0.0, 1.0),
+			sphere(4.0, 1.0),
+		);
+		assert!(intersection.get_spans(&along_x(-5.0)).is_empty());
+	}
+
+	#[test]
+	fn rays_starting_inside_hit_the_exit() {
+		let ray = along_x(0.5);
+		let union = csg(CsgOperation::Union);
+		assert!((union.hit(&ray).unwrap().distance - 1.5).abs() < 1e-9);
+		let intersection = csg(CsgOperation::Intersection);
+		assert!((intersection.hit(&ray).unwrap().distance - 0.5).abs() < 1e-9);
+		// starts inside of the carved out part, nothing of the difference is left ahead
+		let difference = csg(CsgOperation::Difference);
+		assert!(difference.hit(&ray).is_none());
+		// starts inside of the difference, hits where the right sphere carves it out
+		let ray = along_x(-0.5);
+		assert!((difference.hit(&ray).unwrap().distance - 0.5).abs() < 1e-9);
+	}
+
+	#[test]
+	fn hit_carries_the_crossing() {
+		let ray = along_x(-5.0);
+		let intersection = csg(CsgOperation::Intersection);
+		let hit = intersection.hit(&ray).unwrap();
+		let crossing = hit.crossing.unwrap();
+		assert_eq!(crossing.distance, hit.distance);
+		// the entry of the intersection is on the right sphere
+		assert!((crossing.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+	}
+}
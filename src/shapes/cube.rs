@@ -1,6 +1,8 @@
-use crate::{AABB, Bounded, Hittable, Material, Ray, Renderable, Scalar, Vec3};
+use crate::{
+	AABB, Bounded, Crossing, Hit, Hittable, Material, Ray, Renderable, Scalar, Solid, Span, Vec3,
+};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct Cube {
 	pub center: Vec3,
 	pub material: Material,
@@ -21,7 +23,7 @@ impl Cube {
 
 impl Hittable for Cube {
 	#[inline(always)]
-	fn hit(&self, ray: &Ray) -> Option<Scalar> {
+	fn hit(&self, ray: &Ray) -> Option<Hit<'_>> {
 		let origin = ray.origin - self.center;
 
 		let m = 1.0 / ray.dir;
@@ -34,10 +36,13 @@ impl Hittable for Cube {
 		let t_n: Scalar = t1.x.max(t1.y).max(t1.z);
 		let t_f: Scalar = t2.x.min(t2.y).min(t2.z);
 
-		if t_n > t_f || t_f <= 0. || t_n <= 0. {
+		if t_n > t_f || t_f <= 0. {
 			None
+		} else if t_n > 0.001 {
+			Some(Hit::at(t_n))
 		} else {
-			Some(t_n)
+			// rays starting inside the cube hit the far side
+			Some(Hit::at(t_f))
 		}
 	}
 }
@@ -52,7 +57,8 @@ impl Bounded for Cube {
 impl Renderable for Cube {
 	#[inline(always)]
 	fn get_normal(&self, p: &Vec3, _ray: &Ray) -> Vec3 {
-		let rel_p = *p - self.center;
+		// relative to the size, so the faces of flat cubes are found correctly
+		let rel_p = (*p - self.center) / self.half_extend.abs();
 		let maxc = rel_p.x.abs().max(rel_p.y.abs()).max(rel_p.z.abs());
 		if maxc == rel_p.x.abs() {
 			return Vec3::new(rel_p.x.signum(), 0.0, 0.0);
@@ -64,7 +70,78 @@ impl Renderable for Cube {
 	}
 
 	#[inline(always)]
-	fn get_material(&self) -> Option<&Material> {
+	fn get_material(&self, _p: &Vec3, _ray: &Ray) -> Option<&Material> {
 		Some(&self.material)
 	}
+
+	// every face is mapped to the whole texture
+	#[inline(always)]
+	fn get_surface_frame(&self, p: &Vec3, ray: &Ray) -> ((Scalar, Scalar), Vec3) {
+		let normal = self.get_normal(p, ray);
+		let local = (*p - self.center) / self.half_extend.abs();
+		let to_uv = |value: Scalar| (value + 1.0) * 0.5;
+		if normal.x != 0.0 {
+			(
+				(to_uv(-normal.x * local.z), to_uv(-local.y)),
+				Vec3::new(0.0, 0.0, -normal.x),
+			)
+		} else if normal.y != 0.0 {
+			(
+				(to_uv(local.x), to_uv(normal.y * local.z)),
+				Vec3::new(1.0, 0.0, 0.0),
+			)
+		} else {
+			(
+				(to_uv(normal.z * local.x), to_uv(-local.y)),
+				Vec3::new(normal.z, 0.0, 0.0),
+			)
+		}
+	}
+}
+
+impl Solid for Cube {
+	fn get_spans(&self, ray: &Ray) -> Vec<Span<'_>> {
+		let origin = ray.origin - self.center;
+
+		let m = 1.0 / ray.dir;
+		let n: Vec3 = m * origin;
+		// a negative extend describes the same box
+		let k: Vec3 = m.abs() * self.half_extend.abs();
+
+		let t1: Vec3 = -n - k;
+		let t2: Vec3 = -n + k;
+
+		let t_n: Scalar = t1.x.max(t1.y).max(t1.z);
+		let t_f: Scalar = t2.x.min(t2.y).min(t2.z);
+
+		if t_n >= t_f {
+			return Vec::new();
+		}
+
+		let crossing = |distance: Scalar| Crossing {
+			distance,
+			normal: self.get_normal(&ray.at(distance), ray),
+			material: &self.material,
+			shape: self,
+			offset: Vec3::zero(),
+		};
+		vec![Span {
+			enter: crossing(t_n),
+			exit: crossing(t_f),
+		}]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ray_from_inside_hits_the_far_side() {
+		let material = Material::Dielectric { ir: 1.5 };
+		let cube = Cube::new(Vec3::zero(), Vec3::new(2.0, 1.0, 1.0), material);
+		let ray = Ray::new(Vec3::new(0.5, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+		let hit = cube.hit(&ray).unwrap();
+		assert!((hit.distance - 2.5).abs() < 1e-9);
+	}
 }
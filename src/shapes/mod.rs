@@ -2,3 +2,7 @@ mod cube;
 pub use cube::Cube;
 mod sphere;
 pub use sphere::Sphere;
+mod csg;
+pub use csg::{Csg, CsgOperation};
+mod moving;
+pub use moving::Moving;
@@ -0,0 +1,94 @@
+use crate::{
+	AABB, Bounded, Crossing, Hit, Hittable, Material, Object, Ray, Renderable, Scalar, Solid, Span,
+	Vec3,
+};
+
+// Object moving with a constant velocity while the shutter is open (motion blur), at ray time t
+// it is offset by velocity * t
+#[derive(Clone, Debug)]
+pub struct Moving {
+	pub object: Object,
+	// scene units per second
+	pub velocity: Vec3,
+	// the longest time a ray can have (the shutter time of the camera)
+	pub duration: Scalar,
+	aabb: AABB,
+}
+
+impl Moving {
+	pub fn new(object: Object, velocity: Vec3, duration: Scalar) -> Self {
+		let start = object.get_aabb();
+		let offset = velocity * duration;
+		let end = AABB::new(start.min + offset, start.max + offset);
+		Moving {
+			object,
+			velocity,
+			duration,
+			aabb: AABB::surrounding(start, end),
+		}
+	}
+
+	// the ray relative to the object at the time of the ray, distances along it stay the same
+	#[inline(always)]
+	fn local_ray(&self, ray: &Ray) -> Ray {
+		Ray {
+			origin: ray.origin - self.velocity * ray.time,
+			..*ray
+		}
+	}
+
+	// crossings found with the local ray are on the object at its offset at the time of the ray
+	#[inline(always)]
+	fn move_crossing(&self, crossing: &mut Crossing, ray: &Ray) {
+		crossing.offset = crossing.offset + self.velocity * ray.time;
+	}
+}
+
+impl Hittable for Moving {
+	#[inline(always)]
+	fn hit(&self, ray: &Ray) -> Option<Hit<'_>> {
+		let mut hit = self.object.hit(&self.local_ray(ray))?;
+		if let Some(crossing) = &mut hit.crossing {
+			self.move_crossing(crossing, ray);
+		}
+		Some(hit)
+	}
+}
+
+impl Bounded for Moving {
+	#[inline(always)]
+	fn get_aabb(&self) -> AABB {
+		self.aabb
+	}
+}
+
+impl Renderable for Moving {
+	#[inline(always)]
+	fn get_normal(&self, p: &Vec3, ray: &Ray) -> Vec3 {
+		self.object
+			.get_normal(&(*p - self.velocity * ray.time), &self.local_ray(ray))
+	}
+
+	#[inline(always)]
+	fn get_material(&self, p: &Vec3, ray: &Ray) -> Option<&Material> {
+		self.object
+			.get_material(&(*p - self.velocity * ray.time), &self.local_ray(ray))
+	}
+
+	#[inline(always)]
+	fn get_surface_frame(&self, p: &Vec3, ray: &Ray) -> ((Scalar, Scalar), Vec3) {
+		self.object
+			.get_surface_frame(&(*p - self.velocity * ray.time), &self.local_ray(ray))
+	}
+}
+
+impl Solid for Moving {
+	fn get_spans(&self, ray: &Ray) -> Vec<Span<'_>> {
+		let mut spans = self.object.get_spans(&self.local_ray(ray));
+		for span in spans.iter_mut() {
+			self.move_crossing(&mut span.enter, ray);
+			self.move_crossing(&mut span.exit, ray);
+		}
+		spans
+	}
+}
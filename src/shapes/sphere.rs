@@ -1,6 +1,9 @@
-use crate::{AABB, Bounded, Hittable, Material, Ray, Renderable, Scalar, Vec3};
+use crate::{
+	AABB, Bounded, Crossing, Hit, Hittable, Material, Ray, Renderable, Scalar, Solid, Span, Vec3,
+	math::PI,
+};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct Sphere {
 	pub center: Vec3,
 	pub material: Material,
@@ -23,7 +26,7 @@ impl Sphere {
 
 impl Hittable for Sphere {
 	#[inline(always)]
-	fn hit(&self, ray: &Ray) -> Option<Scalar> {
+	fn hit(&self, ray: &Ray) -> Option<Hit<'_>> {
 		// a = ray origin
 		// b = ray direction
 		// r = radius
@@ -44,7 +47,13 @@ impl Hittable for Sphere {
 		}
 
 		// (-b +- sqrt(discriminant)) / 2a
-		Some((-b - discriminant.sqrt()) / (2.0 * a))
+		// rays starting inside the sphere hit the far side
+		let near = (-b - discriminant.sqrt()) / (2.0 * a);
+		if near > 0.001 {
+			Some(Hit::at(near))
+		} else {
+			Some(Hit::at((-b + discriminant.sqrt()) / (2.0 * a)))
+		}
 	}
 }
 
@@ -62,7 +71,66 @@ impl Renderable for Sphere {
 	}
 
 	#[inline(always)]
-	fn get_material(&self) -> Option<&Material> {
+	fn get_material(&self, _p: &Vec3, _ray: &Ray) -> Option<&Material> {
 		Some(&self.material)
 	}
+
+	// longitude/latitude mapping around the y axis
+	#[inline(always)]
+	fn get_surface_frame(&self, p: &Vec3, _ray: &Ray) -> ((Scalar, Scalar), Vec3) {
+		let dir = (*p - self.center).normalize();
+		let u = 0.5 - dir.z.atan2(dir.x) / (2.0 * PI);
+		let v = 0.5 - dir.y.clamp(-1.0, 1.0).asin() / PI;
+		let tangent = Vec3::new(dir.z, 0.0, -dir.x);
+		// the poles have no defined direction of increasing u
+		let tangent = if tangent.near_zero() {
+			Vec3::new(1.0, 0.0, 0.0)
+		} else {
+			tangent.normalize()
+		};
+		((u, v), tangent)
+	}
+}
+
+impl Solid for Sphere {
+	fn get_spans(&self, ray: &Ray) -> Vec<Span<'_>> {
+		// negative radius only flips the normals in get_normal, the volume stays the same
+		let radius = self.radius.abs();
+		let origin = ray.origin - self.center;
+
+		let a = ray.dir.dot(ray.dir);
+		let b = 2.0 * origin.dot(ray.dir);
+		let c = origin.dot(origin) - radius * radius;
+
+		let discriminant = b * b - 4.0 * a * c;
+		if discriminant <= 0.0 {
+			return Vec::new();
+		}
+
+		let crossing = |distance: Scalar| Crossing {
+			distance,
+			normal: (ray.at(distance) - self.center) / radius,
+			material: &self.material,
+			shape: self,
+			offset: Vec3::zero(),
+		};
+		vec![Span {
+			enter: crossing((-b - discriminant.sqrt()) / (2.0 * a)),
+			exit: crossing((-b + discriminant.sqrt()) / (2.0 * a)),
+		}]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ray_from_inside_hits_the_far_side() {
+		let material = Material::Dielectric { ir: 1.5 };
+		let sphere = Sphere::new(Vec3::zero(), 2.0, material);
+		let ray = Ray::new(Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+		let hit = sphere.hit(&ray).unwrap();
+		assert!((hit.distance - 1.0).abs() < 1e-9);
+	}
 }
@@ -0,0 +1,334 @@
+use crate::{Scalar, Vec3};
+use std::sync::OnceLock;
+
+// visible range covered by the cie color matching functions, in nanometers
+pub const WAVELENGTH_MIN: Scalar = 360.0;
+pub const WAVELENGTH_MAX: Scalar = 830.0;
+// wavelength of the sodium d-line, the ior used by the rgb renderer
+pub const WAVELENGTH_D_LINE: Scalar = 587.6;
+
+// wavelengths traced together along one path, the first one is the hero wavelength
+pub const SPECTRAL_SAMPLES: usize = 4;
+
+// Index of refraction, optionally depending on the wavelength
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ior {
+	Constant(Scalar),
+	// n = a + b / λ², λ in micrometers
+	Cauchy { a: Scalar, b: Scalar },
+	// n² = 1 + Σ b_i λ² / (λ² - c_i), λ in micrometers
+	Sellmeier { b: [Scalar; 3], c: [Scalar; 3] },
+}
+
+impl Ior {
+	// borosilicate crown glass, common optical glass
+	pub const BK7: Self = Self::Sellmeier {
+		b: [1.03961212, 0.231792344, 1.01046945],
+		c: [0.00600069867, 0.0200179144, 103.560653],
+	};
+	pub const FUSED_SILICA: Self = Self::Sellmeier {
+		b: [0.6961663, 0.4079426, 0.8974794],
+		c: [0.00467914826, 0.0135120631, 97.9340025],
+	};
+	// SF11, disperses a lot more than crown glass
+	pub const DENSE_FLINT: Self = Self::Sellmeier {
+		b: [1.73759695, 0.313747346, 1.89878101],
+		c: [0.013188707, 0.0623068142, 155.23629],
+	};
+	pub const DIAMOND: Self = Self::Cauchy {
+		a: 2.385,
+		b: 0.0117,
+	};
+
+	#[inline(always)]
+	pub fn at(&self, wavelength: Scalar) -> Scalar {
+		let micrometers = wavelength / 1000.0;
+		let l2 = micrometers * micrometers;
+		match self {
+			Self::Constant(ior) => *ior,
+			Self::Cauchy { a, b } => a + b / l2,
+			Self::Sellmeier { b, c } => {
+				let n2 = 1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<Scalar>();
+				n2.sqrt()
+			}
+		}
+	}
+
+	pub fn is_dispersive(&self) -> bool {
+		!matches!(self, Self::Constant(_))
+	}
+}
+
+// Wavelengths of one path and the probability density they were sampled with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampledWavelengths {
+	pub lambda: [Scalar; SPECTRAL_SAMPLES],
+	pub pdf: [Scalar; SPECTRAL_SAMPLES],
+}
+
+impl SampledWavelengths {
+	// hero wavelength sampling: the other wavelengths are evenly rotated through the visible range
+	pub fn sample(u: Scalar) -> Self {
+		let range = WAVELENGTH_MAX - WAVELENGTH_MIN;
+		let mut lambda = [0.0; SPECTRAL_SAMPLES];
+		for (i, lambda) in lambda.iter_mut().enumerate() {
+			let offset = (u + i as Scalar / SPECTRAL_SAMPLES as Scalar).fract();
+			*lambda = WAVELENGTH_MIN + offset * range;
+		}
+		Self {
+			lambda,
+			pdf: [1.0 / range; SPECTRAL_SAMPLES],
+		}
+	}
+
+	pub fn hero(&self) -> Scalar {
+		self.lambda[0]
+	}
+
+	// after a wavelength dependent scattering event only the hero wavelength can continue the path
+	pub fn terminate_secondary(&mut self) {
+		if self.pdf[1..].iter().all(|pdf| *pdf == 0.0) {
+			return;
+		}
+		self.pdf[0] /= SPECTRAL_SAMPLES as Scalar;
+		self.pdf[1..].fill(0.0);
+	}
+}
+
+// Spectral power or reflectance at the sampled wavelengths
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampledSpectrum(pub [Scalar; SPECTRAL_SAMPLES]);
+
+impl SampledSpectrum {
+	pub fn uniform(value: Scalar) -> Self {
+		Self([value; SPECTRAL_SAMPLES])
+	}
+
+	pub fn max_value(&self) -> Scalar {
+		self.0.iter().copied().fold(Scalar::MIN, Scalar::max)
+	}
+
+	// upsamples a linear rgb color with Smits' method (1999), works for reflectances and emitters
+	pub fn from_rgb(rgb: Vec3, wavelengths: &SampledWavelengths) -> Self {
+		Self(
+			wavelengths
+				.lambda
+				.map(|lambda| rgb_to_spectrum(rgb, lambda)),
+		)
+	}
+
+	// converts the monte carlo estimate of the spectrum to linear srgb,
+	// a constant spectrum of 1 maps to white
+	pub fn to_rgb(&self, wavelengths: &SampledWavelengths) -> Vec3 {
+		let mut xyz = Vec3::zero();
+		for i in 0..SPECTRAL_SAMPLES {
+			if wavelengths.pdf[i] == 0.0 {
+				continue;
+			}
+			xyz = xyz + cie_xyz(wavelengths.lambda[i]) * (self.0[i] / wavelengths.pdf[i]);
+		}
+		xyz_to_linear_srgb(xyz / SPECTRAL_SAMPLES as Scalar) * white_balance()
+	}
+}
+
+impl std::ops::Mul for SampledSpectrum {
+	type Output = Self;
+
+	#[inline(always)]
+	fn mul(self, rhs: Self) -> Self {
+		Self(std::array::from_fn(|i| self.0[i] * rhs.0[i]))
+	}
+}
+
+impl std::ops::Mul<Scalar> for SampledSpectrum {
+	type Output = Self;
+
+	#[inline(always)]
+	fn mul(self, rhs: Scalar) -> Self {
+		Self(self.0.map(|value| value * rhs))
+	}
+}
+
+impl std::ops::Div<Scalar> for SampledSpectrum {
+	type Output = Self;
+
+	#[inline(always)]
+	fn div(self, rhs: Scalar) -> Self {
+		Self(self.0.map(|value| value / rhs))
+	}
+}
+
+// integrates a reflectance spectrum over the visible range in equally sized wavelength bands,
+// a constant reflectance maps to the same gray value in rgb
+pub fn reflectance_to_rgb(bands: usize, reflectance: impl Fn(Scalar) -> Scalar) -> Vec3 {
+	let step = (WAVELENGTH_MAX - WAVELENGTH_MIN) / bands as Scalar;
+	let mut xyz = Vec3::zero();
+	for i in 0..bands {
+		let wavelength = WAVELENGTH_MIN + (i as Scalar + 0.5) * step;
+		xyz = xyz + cie_xyz(wavelength) * (reflectance(wavelength) * step);
+	}
+	xyz_to_linear_srgb(xyz) * white_balance()
+}
+
+// film gains that make a black body of the given temperature (in kelvin) look like daylight
+// (6500K), normalized to keep the luminance
+pub fn white_balance_gains(temperature: Scalar) -> Vec3 {
+	let reference = black_body_rgb(6500.0);
+	let color = black_body_rgb(temperature.clamp(1000.0, 40000.0));
+	// very warm black bodies are outside of the srgb gamut
+	let gains = Vec3::new(
+		reference.x / color.x.max(1e-3),
+		reference.y / color.y.max(1e-3),
+		reference.z / color.z.max(1e-3),
+	);
+	gains / gains.luminance()
+}
+
+// linear rgb of a black body, normalized to a luminance of 1
+fn black_body_rgb(temperature: Scalar) -> Vec3 {
+	// planck's law without the constant factor, wavelength in meters
+	const H_C_OVER_K: Scalar = 1.4387769e-2;
+	let rgb = reflectance_to_rgb(64, |wavelength| {
+		let lambda = wavelength * 1e-9;
+		1.0 / (lambda.powi(5) * ((H_C_OVER_K / (lambda * temperature)).exp() - 1.0))
+	});
+	rgb / rgb.luminance()
+}
+
+// cie 1931 color matching functions, multi-lobe gaussian fit by Wyman, Sloan and Shirley (2013)
+fn cie_xyz(wavelength: Scalar) -> Vec3 {
+	let lobe = |mean: Scalar, sigma_low: Scalar, sigma_high: Scalar| {
+		let sigma = if wavelength < mean {
+			sigma_low
+		} else {
+			sigma_high
+		};
+		let t = (wavelength - mean) / sigma;
+		(-0.5 * t * t).exp()
+	};
+	Vec3::new(
+		1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+			- 0.065 * lobe(501.1, 20.4, 26.2),
+		0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1),
+		1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8),
+	)
+}
+
+fn xyz_to_linear_srgb(xyz: Vec3) -> Vec3 {
+	Vec3::new(
+		3.2404542 * xyz.x - 1.5371385 * xyz.y - 0.4985314 * xyz.z,
+		-0.9692660 * xyz.x + 1.8760108 * xyz.y + 0.0415560 * xyz.z,
+		0.0556434 * xyz.x - 0.2040259 * xyz.y + 1.0572252 * xyz.z,
+	)
+}
+
+// maps the equal energy white point to rgb (1, 1, 1), integrated once over the visible range
+fn white_balance() -> Vec3 {
+	static WHITE_BALANCE: OnceLock<Vec3> = OnceLock::new();
+	*WHITE_BALANCE.get_or_init(|| {
+		let steps = 4700;
+		let step = (WAVELENGTH_MAX - WAVELENGTH_MIN) / steps as Scalar;
+		let mut xyz = Vec3::zero();
+		for i in 0..steps {
+			xyz = xyz + cie_xyz(WAVELENGTH_MIN + (i as Scalar + 0.5) * step) * step;
+		}
+		let white = xyz_to_linear_srgb(xyz);
+		Vec3::new(1.0 / white.x, 1.0 / white.y, 1.0 / white.z)
+	})
+}
+
+// Smits' basis spectra, 10 equally sized bins between 380nm and 720nm
+const SMITS_MIN: Scalar = 380.0;
+const SMITS_MAX: Scalar = 720.0;
+const SMITS_WHITE: [Scalar; 10] = [
+	1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [Scalar; 10] = [
+	0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [Scalar; 10] = [
+	1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [Scalar; 10] = [
+	0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [Scalar; 10] = [
+	0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [Scalar; 10] = [
+	0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [Scalar; 10] = [
+	1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+pub(crate) fn rgb_to_spectrum(rgb: Vec3, wavelength: Scalar) -> Scalar {
+	// wavelengths outside of the table use the nearest bin
+	let bin = (((wavelength - SMITS_MIN) / (SMITS_MAX - SMITS_MIN) * 10.0).floor() as isize)
+		.clamp(0, 9) as usize;
+	let white = SMITS_WHITE[bin];
+	let (r, g, b) = (rgb.x, rgb.y, rgb.z);
+	if r <= g && r <= b {
+		r * white
+			+ if g <= b {
+				(g - r) * SMITS_CYAN[bin] + (b - g) * SMITS_BLUE[bin]
+			} else {
+				(b - r) * SMITS_CYAN[bin] + (g - b) * SMITS_GREEN[bin]
+			}
+	} else if g <= r && g <= b {
+		g * white
+			+ if r <= b {
+				(r - g) * SMITS_MAGENTA[bin] + (b - r) * SMITS_BLUE[bin]
+			} else {
+				(b - g) * SMITS_MAGENTA[bin] + (r - b) * SMITS_RED[bin]
+			}
+	} else {
+		b * white
+			+ if r <= g {
+				(r - b) * SMITS_YELLOW[bin] + (g - r) * SMITS_GREEN[bin]
+			} else {
+				(g - b) * SMITS_YELLOW[bin] + (r - g) * SMITS_RED[bin]
+			}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_close(actual: Scalar, expected: Scalar, tolerance: Scalar) {
+		assert!(
+			(actual - expected).abs() < tolerance,
+			"{actual} != {expected}"
+		);
+	}
+
+	#[test]
+	fn sellmeier_matches_catalog_values() {
+		// refractive indices at the d, f and c lines from the glass catalogs
+		assert_close(Ior::BK7.at(WAVELENGTH_D_LINE), 1.5168, 1e-4);
+		assert_close(Ior::BK7.at(486.13), 1.52238, 1e-4);
+		assert_close(Ior::BK7.at(656.27), 1.51432, 1e-4);
+		assert_close(Ior::FUSED_SILICA.at(WAVELENGTH_D_LINE), 1.4585, 1e-4);
+		assert_close(Ior::DENSE_FLINT.at(WAVELENGTH_D_LINE), 1.78472, 1e-4);
+	}
+
+	#[test]
+	fn cauchy_follows_its_formula() {
+		let ior = Ior::Cauchy { a: 1.5, b: 0.01 };
+		// λ = 0.5 µm, n = 1.5 + 0.01 / 0.25
+		assert_close(ior.at(500.0), 1.54, 1e-12);
+		assert_close(Ior::DIAMOND.at(WAVELENGTH_D_LINE), 2.419, 1e-3);
+	}
+
+	#[test]
+	fn dispersive_iors_decrease_with_the_wavelength() {
+		for ior in [Ior::BK7, Ior::FUSED_SILICA, Ior::DENSE_FLINT, Ior::DIAMOND] {
+			assert!(ior.is_dispersive());
+			assert!(ior.at(400.0) > ior.at(550.0));
+			assert!(ior.at(550.0) > ior.at(700.0));
+		}
+		assert!(!Ior::Constant(1.5).is_dispersive());
+		assert_eq!(Ior::Constant(1.5).at(400.0), 1.5);
+	}
+}
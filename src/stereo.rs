@@ -0,0 +1,133 @@
+use crate::{Camera, Projection, Ray, Sampler, Scalar, Vec3, camera::camera_basis, math::PI};
+use std::ops::Range;
+
+// both eyes of a stereo image are stacked top-bottom (left eye on top), the image is twice as high
+// as one eye
+fn eye_of_row(y: Scalar, eye_height: usize) -> (bool, Scalar) {
+	if y < eye_height as Scalar {
+		(true, y)
+	} else {
+		(false, y - eye_height as Scalar)
+	}
+}
+
+// the rows of the eye of pixel row y, open towards the outer edges of the film where the eye's
+// camera still sees something
+fn eye_rows(y: usize, eye_height: usize) -> Range<Scalar> {
+	if y < eye_height {
+		Scalar::NEG_INFINITY..eye_height as Scalar
+	} else {
+		eye_height as Scalar..Scalar::INFINITY
+	}
+}
+
+// Two parallel pinhole cameras ipd apart, their image planes meet at the convergence distance
+// (objects there have no parallax, closer ones pop out of the screen)
+#[derive(Debug, Clone, PartialEq)]
+pub struct StereoCamera {
+	left: Camera,
+	right: Camera,
+	eye_height: usize,
+}
+
+impl StereoCamera {
+	// average human interpupillary distance in meters
+	pub const DEFAULT_IPD: Scalar = 0.064;
+	pub const DEFAULT_CONVERGENCE_DIST: Scalar = 10.0;
+
+	// width and height are the size of one eye
+	pub fn new(
+		origin: Vec3,
+		direction: Vec3,
+		fov: Scalar,
+		ipd: Scalar,
+		convergence_dist: Scalar,
+		width: usize,
+		height: usize,
+	) -> Self {
+		let center = Camera::new(origin, direction, fov, convergence_dist, 0.0, width, height);
+		let (u, _, _) = camera_basis(direction);
+		Self {
+			left: center.with_eye_offset(-u * (ipd / 2.0)),
+			right: center.with_eye_offset(u * (ipd / 2.0)),
+			eye_height: height,
+		}
+	}
+
+	pub fn left(&self) -> &Camera {
+		&self.left
+	}
+
+	pub fn right(&self) -> &Camera {
+		&self.right
+	}
+}
+
+impl Projection for StereoCamera {
+	fn get_ray(&self, x: Scalar, y: Scalar, sampler: &mut dyn Sampler) -> Option<Ray> {
+		let (is_left, y) = eye_of_row(y, self.eye_height);
+		if is_left {
+			self.left.get_ray(x, y, sampler)
+		} else {
+			self.right.get_ray(x, y, sampler)
+		}
+	}
+
+	fn image_rows(&self, y: usize) -> Range<Scalar> {
+		eye_rows(y, self.eye_height)
+	}
+}
+
+// Omni-directional stereo: a latitude-longitude panorama per eye where every column is seen from
+// an eye on a circle of diameter ipd, so the parallax is right in every viewing direction.
+// The eye separation fades out towards the poles to avoid the swirl there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OdsCamera {
+	origin: Vec3,
+	u: Vec3,
+	v: Vec3,
+	w: Vec3,
+	ipd: Scalar,
+	width: usize,
+	eye_height: usize,
+}
+
+impl OdsCamera {
+	// width and height are the size of one eye, width should be twice the height
+	pub fn new(origin: Vec3, direction: Vec3, ipd: Scalar, width: usize, height: usize) -> Self {
+		let (u, v, w) = camera_basis(direction);
+		Self {
+			origin,
+			u,
+			v,
+			w,
+			ipd,
+			width,
+			eye_height: height,
+		}
+	}
+}
+
+impl Projection for OdsCamera {
+	fn get_ray(&self, x: Scalar, y: Scalar, _sampler: &mut dyn Sampler) -> Option<Ray> {
+		let (is_left, y) = eye_of_row(y, self.eye_height);
+		let longitude = (x / self.width as Scalar - 0.5) * 2.0 * PI;
+		let latitude = (0.5 - y / self.eye_height as Scalar) * PI;
+
+		let horizontal = -self.w * longitude.cos() + self.u * longitude.sin();
+		let dir = horizontal * latitude.cos() + self.v * latitude.sin();
+		// the eyes sit on the circle, perpendicular to the horizontal view direction
+		let right = self.u * longitude.cos() + self.w * longitude.sin();
+		let eye_offset = right * (self.ipd / 2.0 * latitude.cos());
+		let origin = if is_left {
+			self.origin - eye_offset
+		} else {
+			self.origin + eye_offset
+		};
+		Some(Ray::new(origin, dir))
+	}
+
+	fn image_rows(&self, y: usize) -> Range<Scalar> {
+		eye_rows(y, self.eye_height)
+	}
+}
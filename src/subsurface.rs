@@ -0,0 +1,121 @@
+use crate::{BVH, Ray, Sampler, Scalar, Scattered, Vec3};
+
+// a walk that is still inside after this many scattering events is absorbed
+const MAX_WALK_STEPS: u32 = 256;
+
+// Follows a ray that refracted into a subsurface object through its inside until it leaves again.
+// The medium scatters isotropically, albedo is the single scattering albedo and mean_free_path the
+// average distance between scattering events per color channel.
+// Returns the ray leaving the object together with the throughput of the walk, None if absorbed.
+pub(crate) fn random_walk(
+	ray: Ray,
+	bvh: &BVH,
+	albedo: Vec3,
+	mean_free_path: Vec3,
+	sampler: &mut dyn Sampler,
+) -> Option<Scattered> {
+	let extinction = Vec3::one() / mean_free_path.clamp(1e-6, Scalar::INFINITY);
+	let scattering = extinction * albedo;
+
+	// the walk happens at the time of the path, the renderer keeps it for the scattered ray
+	let time = ray.time;
+	let mut ray = Ray::new(ray.origin, ray.dir.normalize()).with_time(time);
+	let mut throughput = Vec3::one();
+	for _ in 0..MAX_WALK_STEPS {
+		// the walk only works in closed objects, open ones let the path escape into nothing
+		let hit = bvh.trace(&ray, sampler)?;
+		let surface_distance = (hit.point - ray.origin).length();
+
+		// distances are sampled with the extinction of a random channel, the throughput is weighted
+		// with the average pdf of all channels (spectral mis)
+		let channel = ((sampler.get_1d() * 3.0) as usize).min(2);
+		let distance = -(1.0 - sampler.get_1d()).ln() / extinction[channel];
+
+		if distance < surface_distance {
+			let transmittance = exp(-extinction * distance);
+			let pdf = average(extinction * transmittance);
+			throughput = throughput * scattering * transmittance / pdf;
+
+			let survival_probability = throughput.max_component().min(1.0);
+			if sampler.get_1d() >= survival_probability {
+				return None;
+			}
+			throughput = throughput / survival_probability;
+
+			ray = Ray::new(ray.at(distance), Vec3::sample_unit_vector(sampler.get_2d()))
+				.with_time(time);
+			continue;
+		}
+
+		let transmittance = exp(-extinction * surface_distance);
+		throughput = throughput * transmittance / average(transmittance);
+
+		// something else is inside of the object, the path continues at that surface
+		if hit.front_face || hit.material.subsurface_medium().is_none() {
+			return Some(Scattered {
+				attenuation: throughput,
+				scattered: ray,
+			});
+		}
+
+		// the boundary either lets the walk leave or reflects it back inside
+		let boundary = hit.material.scatter(&ray, &hit, sampler)?;
+		throughput = throughput * boundary.attenuation;
+		if boundary.scattered.dir.dot(hit.normal) < 0.0 {
+			return Some(Scattered {
+				attenuation: throughput,
+				scattered: boundary.scattered,
+			});
+		}
+		ray = Ray::new(
+			boundary.scattered.origin,
+			boundary.scattered.dir.normalize(),
+		)
+		.with_time(time);
+	}
+	None
+}
+
+#[inline(always)]
+fn exp(v: Vec3) -> Vec3 {
+	Vec3::new(v.x.exp(), v.y.exp(), v.z.exp())
+}
+
+#[inline(always)]
+fn average(v: Vec3) -> Scalar {
+	(v.x + v.y + v.z) / 3.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{IndependentSampler, Material, ScalarTexture, Scene};
+
+	#[test]
+	fn walk_leaves_through_a_wrapped_subsurface_boundary() {
+		let mut scene = Scene::new(Vec::new());
+		let medium = Material::Subsurface {
+			albedo: Vec3::one(),
+			mean_free_path: Vec3::uniform(1e9),
+			ir: 1.0,
+		};
+		scene.add_sphere(
+			Vec3::zero(),
+			1.0,
+			medium.with_opacity(ScalarTexture::Constant(1.0)),
+		);
+		let bvh = BVH::new(scene).unwrap();
+
+		let ray = Ray::new(Vec3::zero(), Vec3::new(1.0, 0.0, 0.0));
+		let walk = random_walk(
+			ray,
+			&bvh,
+			Vec3::one(),
+			Vec3::uniform(1e9),
+			&mut IndependentSampler::new(),
+		)
+		.unwrap();
+		assert!((walk.scattered.origin.x - 1.0).abs() < 1e-6);
+		assert!(walk.scattered.dir.x > 0.0);
+	}
+}
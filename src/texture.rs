@@ -0,0 +1,151 @@
+use crate::{Scalar, Vec3};
+use image::error::{ParameterError, ParameterErrorKind};
+use std::{path::Path, sync::Arc};
+
+// Image sampled with wrapping texture coordinates, values are stored as loaded (no gamma conversion)
+#[derive(Clone)]
+pub struct ImageTexture {
+	pub width: usize,
+	pub height: usize,
+	pub pixels: Vec<Vec3>,
+}
+
+impl ImageTexture {
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, image::ImageError> {
+		let image = non_empty(image::open(path)?)?.into_rgb32f();
+		Ok(Self {
+			width: image.width() as usize,
+			height: image.height() as usize,
+			pixels: image
+				.pixels()
+				.map(|pixel| Vec3::new(pixel[0] as Scalar, pixel[1] as Scalar, pixel[2] as Scalar))
+				.collect(),
+		})
+	}
+
+	// alpha channel of the image as a gray texture, opaque images load as white
+	pub fn open_alpha(path: impl AsRef<Path>) -> Result<Self, image::ImageError> {
+		let image = non_empty(image::open(path)?)?.into_rgba32f();
+		Ok(Self {
+			width: image.width() as usize,
+			height: image.height() as usize,
+			pixels: image
+				.pixels()
+				.map(|pixel| Vec3::uniform(pixel[3] as Scalar))
+				.collect(),
+		})
+	}
+
+	// procedural texture, f gets the texture coordinates of every texel center, panics if it is
+	// empty
+	pub fn from_fn(width: usize, height: usize, f: impl Fn(Scalar, Scalar) -> Vec3) -> Self {
+		assert!(
+			width > 0 && height > 0,
+			"textures need at least one texel, got {width}x{height}"
+		);
+		let mut pixels = Vec::with_capacity(width * height);
+		for y in 0..height {
+			for x in 0..width {
+				pixels.push(f(
+					(x as Scalar + 0.5) / width as Scalar,
+					(y as Scalar + 0.5) / height as Scalar,
+				));
+			}
+		}
+		Self {
+			width,
+			height,
+			pixels,
+		}
+	}
+
+	// bilinear filtering, v = 0 is the first row
+	pub fn sample(&self, uv: (Scalar, Scalar)) -> Vec3 {
+		let x = uv.0 * self.width as Scalar - 0.5;
+		let y = uv.1 * self.height as Scalar - 0.5;
+		let (x0, y0) = (x.floor(), y.floor());
+		let (tx, ty) = (x - x0, y - y0);
+		let texel = |x: Scalar, y: Scalar| {
+			let x = (x as isize).rem_euclid(self.width as isize) as usize;
+			let y = (y as isize).rem_euclid(self.height as isize) as usize;
+			self.pixels[y * self.width + x]
+		};
+		let top = texel(x0, y0) * (1.0 - tx) + texel(x0 + 1.0, y0) * tx;
+		let bottom = texel(x0, y0 + 1.0) * (1.0 - tx) + texel(x0 + 1.0, y0 + 1.0) * tx;
+		top * (1.0 - ty) + bottom * ty
+	}
+
+	// size of one texel in texture coordinates
+	pub fn texel_size(&self) -> (Scalar, Scalar) {
+		(1.0 / self.width as Scalar, 1.0 / self.height as Scalar)
+	}
+}
+
+// textures without texels can't be sampled
+fn non_empty(image: image::DynamicImage) -> Result<image::DynamicImage, image::ImageError> {
+	if image.width() == 0 || image.height() == 0 {
+		return Err(image::ImageError::Parameter(ParameterError::from_kind(
+			ParameterErrorKind::DimensionMismatch,
+		)));
+	}
+	Ok(image)
+}
+
+// a hash of the texels instead of all of them, checkpoints still see when the content changes
+impl std::fmt::Debug for ImageTexture {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ImageTexture")
+			.field("width", &self.width)
+			.field("height", &self.height)
+			.field(
+				"texel_hash",
+				&format_args!("{:016x}", texel_hash(&self.pixels)),
+			)
+			.finish()
+	}
+}
+
+// FNV-1a over the bits of every texel
+fn texel_hash(pixels: &[Vec3]) -> u64 {
+	let mut hash: u64 = 0xcbf29ce484222325;
+	for pixel in pixels {
+		for value in [pixel.x, pixel.y, pixel.z] {
+			for byte in value.to_bits().to_le_bytes() {
+				hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+			}
+		}
+	}
+	hash
+}
+
+// Scalar material parameter that is either constant or read from the luminance of a texture
+#[derive(Clone, Debug)]
+pub enum ScalarTexture {
+	Constant(Scalar),
+	Texture(Arc<ImageTexture>),
+}
+
+impl ScalarTexture {
+	#[inline(always)]
+	pub fn at(&self, uv: (Scalar, Scalar)) -> Scalar {
+		match self {
+			Self::Constant(value) => *value,
+			Self::Texture(texture) => texture.sample(uv).luminance(),
+		}
+	}
+
+	// average over the whole texture, for when there are no texture coordinates
+	pub fn average(&self) -> Scalar {
+		match self {
+			Self::Constant(value) => *value,
+			Self::Texture(texture) => {
+				texture
+					.pixels
+					.iter()
+					.map(|pixel| pixel.luminance())
+					.sum::<Scalar>()
+					/ texture.pixels.len().max(1) as Scalar
+			}
+		}
+	}
+}
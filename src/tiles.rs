@@ -0,0 +1,188 @@
+// Rectangle of pixels that is rendered as one unit of work, tiles at the right and bottom border
+// can be smaller than the tile size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+	// position in the render order
+	pub index: usize,
+	pub x: usize,
+	pub y: usize,
+	pub width: usize,
+	pub height: usize,
+}
+
+impl Tile {
+	pub fn pixel_count(&self) -> usize {
+		self.width * self.height
+	}
+
+	// (x, y) of every pixel of the tile, row by row
+	pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+		(self.y..self.y + self.height)
+			.flat_map(move |y| (self.x..self.x + self.width).map(move |x| (x, y)))
+	}
+}
+
+// Order the tiles of an image are handed out in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileOrder {
+	// rows from top to bottom, every row from left to right
+	Scanline,
+	// from the center outwards, the interesting part of the image is usually done first
+	#[default]
+	Spiral,
+	// along a hilbert curve, neighbouring tiles are rendered close in time (cache friendly)
+	Hilbert,
+}
+
+impl TileOrder {
+	pub const NAMES: [&'static str; 3] = ["scanline", "spiral", "hilbert"];
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"scanline" => Some(Self::Scanline),
+			"spiral" => Some(Self::Spiral),
+			"hilbert" => Some(Self::Hilbert),
+			_ => None,
+		}
+	}
+
+	// splits the image into tiles of tile_size x tile_size pixels in this order
+	pub fn create_tiles(&self, width: usize, height: usize, tile_size: usize) -> Vec<Tile> {
+		let tile_size = tile_size.max(1);
+		let columns = width.div_ceil(tile_size);
+		let rows = height.div_ceil(tile_size);
+		let positions = match self {
+			Self::Scanline => (0..rows)
+				.flat_map(|row| (0..columns).map(move |column| (column, row)))
+				.collect(),
+			Self::Spiral => spiral_positions(columns, rows),
+			Self::Hilbert => hilbert_positions(columns, rows),
+		};
+		positions
+			.into_iter()
+			.enumerate()
+			.map(|(index, (column, row))| {
+				let x = column * tile_size;
+				let y = row * tile_size;
+				Tile {
+					index,
+					x,
+					y,
+					width: tile_size.min(width - x),
+					height: tile_size.min(height - y),
+				}
+			})
+			.collect()
+	}
+}
+
+// walks right, down, left, up with growing run lengths around the center tile and keeps the
+// positions that are inside of the grid
+fn spiral_positions(columns: usize, rows: usize) -> Vec<(usize, usize)> {
+	let count = columns * rows;
+	let mut positions = Vec::with_capacity(count);
+	let (mut column, mut row) = ((columns as isize - 1) / 2, (rows as isize - 1) / 2);
+	let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+	let mut run_length = 1;
+	let mut direction = 0;
+	while positions.len() < count {
+		// every run length is used twice
+		for _ in 0..2 {
+			let (dx, dy) = directions[direction % 4];
+			for _ in 0..run_length {
+				if column >= 0 && row >= 0 && column < columns as isize && row < rows as isize {
+					positions.push((column as usize, row as usize));
+				}
+				column += dx;
+				row += dy;
+			}
+			direction += 1;
+		}
+		run_length += 1;
+	}
+	positions
+}
+
+// hilbert curve over the smallest power of two square that covers the grid, positions outside of
+// the grid are skipped
+fn hilbert_positions(columns: usize, rows: usize) -> Vec<(usize, usize)> {
+	let size = columns.max(rows).next_power_of_two();
+	(0..size * size)
+		.map(|distance| hilbert_position(size, distance))
+		.filter(|&(column, row)| column < columns && row < rows)
+		.collect()
+}
+
+// position of the distance along the hilbert curve of a size x size grid
+fn hilbert_position(size: usize, distance: usize) -> (usize, usize) {
+	let (mut x, mut y) = (0, 0);
+	let mut t = distance;
+	let mut s = 1;
+	while s < size {
+		let rx = 1 & (t / 2);
+		let ry = 1 & (t ^ rx);
+		// rotate the quadrant
+		if ry == 0 {
+			if rx == 1 {
+				x = s - 1 - x;
+				y = s - 1 - y;
+			}
+			std::mem::swap(&mut x, &mut y);
+		}
+		x += s * rx;
+		y += s * ry;
+		t /= 4;
+		s *= 2;
+	}
+	(x, y)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tiles_cover_every_pixel_once() {
+		let orders = TileOrder::NAMES.map(|name| TileOrder::from_name(name).unwrap());
+		for order in orders {
+			for (width, height, tile_size) in [
+				(64, 64, 16),
+				(100, 37, 16),
+				(37, 100, 32),
+				(7, 5, 8),
+				(1, 1, 4),
+				(5, 3, 0),
+				(130, 20, 1),
+			] {
+				let tiles = order.create_tiles(width, height, tile_size);
+				let mut covered = vec![0; width * height];
+				for (index, tile) in tiles.iter().enumerate() {
+					assert_eq!(tile.index, index);
+					assert!(tile.width > 0 && tile.height > 0, "{order:?} {tile:?}");
+					for (x, y) in tile.pixels() {
+						covered[y * width + x] += 1;
+					}
+				}
+				assert!(
+					covered.iter().all(|&count| count == 1),
+					"{order:?} {width}x{height} tile size {tile_size}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn spiral_starts_in_the_center() {
+		let tiles = TileOrder::Spiral.create_tiles(48, 48, 16);
+		assert_eq!((tiles[0].x, tiles[0].y), (16, 16));
+	}
+
+	#[test]
+	fn hilbert_steps_to_neighbouring_tiles() {
+		let tiles = TileOrder::Hilbert.create_tiles(128, 128, 16);
+		for pair in tiles.windows(2) {
+			let distance = pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y);
+			assert_eq!(distance, 16);
+		}
+	}
+}